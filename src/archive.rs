@@ -0,0 +1,42 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use zip::ZipArchive;
+
+/// Larger entries are not subtitles, even Blu-ray ones stay well below this.
+const MAX_ENTRY_SIZE: u64 = 256 << 20;
+
+/// Names of the files in a ZIP archive, in the order they were packed.
+/// Folders and the resource forks macOS adds under `__MACOSX/` are left out.
+pub fn entries(path: &Path) -> io::Result<Vec<String>> {
+    let archive = open(path)?;
+    Ok((0..archive.len())
+        .filter_map(|index| archive.name_for_index(index))
+        .filter(|name| !name.ends_with('/') && !name.starts_with("__MACOSX/"))
+        .map(String::from)
+        .collect())
+}
+
+/// Unpacks one file of a ZIP archive into memory.
+pub fn read_entry(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let mut archive = open(path)?;
+    let entry = archive.by_name(name)?;
+    // The size in the header can be anything, so it is only a hint
+    let capacity = usize::try_from(entry.size().min(MAX_ENTRY_SIZE)).unwrap_or(0);
+    let mut data = Vec::with_capacity(capacity);
+    entry.take(MAX_ENTRY_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_ENTRY_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "too large for a subtitle file",
+        ));
+    }
+    Ok(data)
+}
+
+fn open(path: &Path) -> io::Result<ZipArchive<BufReader<File>>> {
+    Ok(ZipArchive::new(BufReader::new(File::open(path)?))?)
+}
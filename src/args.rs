@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use iced::Theme;
+
+use crate::Timing;
+
+pub const USAGE: &str = "\
+Usage: iced-subtitle-watcher [OPTIONS] [FILE]...
+
+Options:
+  --offset <TIME>      Offset added to the playback time
+  --start-at <TIME>    Playback time to start from, without the offset
+  --font-size <SIZE>   Subtitle font size, 1 to 99
+  --theme <THEME>      Theme name, like \"Dark\" or \"Tokyo Night\"
+  -h, --help           Show this help
+
+Several files, or a folder, are opened as a playlist of episodes.
+TIME is hh:mm:ss:mmm or a number of milliseconds, a leading - is before the start.";
+
+/// Settings given on the command line, `None` keeps the default.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub files: Vec<PathBuf>,
+    pub offset_ms: Option<i128>,
+    /// The clock time before the offset is added, unlike the seek field.
+    pub start_at_ms: Option<i128>,
+    pub font_size: Option<u16>,
+    pub theme: Option<Theme>,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses the arguments after the program name. Flags take their value
+    /// as the next argument or after `=`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                parsed.help = true;
+                continue;
+            }
+            if !arg.starts_with("--") {
+                parsed.files.push(PathBuf::from(arg));
+                continue;
+            }
+
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), value.to_string()),
+                None => {
+                    let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+                    (arg, value)
+                }
+            };
+            match flag.as_str() {
+                "--offset" => parsed.offset_ms = Some(parse_time(&flag, &value)?),
+                "--start-at" => parsed.start_at_ms = Some(parse_time(&flag, &value)?),
+                "--font-size" => {
+                    parsed.font_size = Some(
+                        value
+                            .parse::<u16>()
+                            .ok()
+                            .filter(|size| (1..100).contains(size))
+                            .ok_or_else(|| {
+                                format!("{flag} must be a number from 1 to 99, got {value}")
+                            })?,
+                    )
+                }
+                "--theme" => parsed.theme = Some(parse_theme(&value)?),
+                _ => return Err(format!("Unknown option {flag}")),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn parse_time(flag: &str, value: &str) -> Result<i128, String> {
+    Timing::from_string_fmtd(value.to_string())
+        .or_else(|| Timing::from_string_ms(value.to_string()))
+        .map(|timing| timing.to_ms())
+        .ok_or_else(|| format!("{flag} must be hh:mm:ss:mmm or milliseconds, got {value}"))
+}
+
+/// Matches theme names ignoring case, spaces, `-` and `_`.
+fn parse_theme(value: &str) -> Result<Theme, String> {
+    let simplify = |name: &str| {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    Theme::ALL
+        .iter()
+        .find(|theme| simplify(&theme.to_string()) == simplify(value))
+        .cloned()
+        .ok_or_else(|| {
+            let names = Theme::ALL
+                .iter()
+                .map(Theme::to_string)
+                .collect::<Vec<String>>();
+            format!("Unknown theme {value}, pick one of: {}", names.join(", "))
+        })
+}
@@ -0,0 +1,731 @@
+use std::collections::HashMap;
+
+use iced::{Color, Point, Size};
+
+use crate::subtitle::{self, Fade, Karaoke, KaraokeKind, Placement, Position, RunStyle, StyledRun};
+
+/// Size of the "Default" style most ASS files are created with.
+pub const DEFAULT_FONT_SIZE: f32 = 20.0;
+
+/// A named style from the `[V4+ Styles]` (or SSA `[V4 Styles]`) section.
+#[derive(Debug, Clone)]
+pub struct AssStyle {
+    pub name: String,
+    pub font_name: String,
+    pub font_size: f32,
+    pub primary_color: Color,
+    pub secondary_color: Color,
+    pub outline_color: Color,
+    pub back_color: Color,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    /// 1 is an outline, 3 is an opaque box behind the text.
+    pub border_style: u8,
+    pub outline: f32,
+    /// Numpad style alignment, SSA values are converted when parsing.
+    pub alignment: u8,
+    pub margin_l: f32,
+    pub margin_r: f32,
+    pub margin_v: f32,
+}
+
+impl Default for AssStyle {
+    fn default() -> Self {
+        AssStyle {
+            name: String::from("Default"),
+            font_name: String::from("Arial"),
+            font_size: DEFAULT_FONT_SIZE,
+            primary_color: Color::WHITE,
+            secondary_color: Color::from_rgb8(255, 0, 0),
+            outline_color: Color::BLACK,
+            back_color: Color::BLACK,
+            bold: false,
+            italic: false,
+            underline: false,
+            strikethrough: false,
+            border_style: 1,
+            outline: 2.0,
+            alignment: 2,
+            margin_l: 10.0,
+            margin_r: 10.0,
+            margin_v: 10.0,
+        }
+    }
+}
+
+impl AssStyle {
+    /// Run styling for text using this style inside a line whose own
+    /// style has `line_font_size`.
+    fn run_style(&self, line_font_size: f32) -> RunStyle {
+        RunStyle {
+            bold: self.bold,
+            italic: self.italic,
+            underline: self.underline,
+            strikethrough: self.strikethrough,
+            color: Some(self.primary_color),
+            secondary_color: Some(self.secondary_color),
+            size: Some(self.font_size / line_font_size).filter(|size| *size != 1.0),
+            family: None,
+        }
+    }
+}
+
+/// The fields of a `Dialogue:` line subparse does not keep.
+#[derive(Debug, Clone, Default)]
+pub struct AssEvent {
+    pub style: String,
+    /// Zero means "use the style's margin".
+    pub margin_l: f32,
+    pub margin_r: f32,
+    pub margin_v: f32,
+}
+
+/// The parts of an ASS/SSA header needed for rendering.
+#[derive(Debug, Clone)]
+pub struct AssScript {
+    pub play_res_x: f32,
+    pub play_res_y: f32,
+    pub styles: Vec<AssStyle>,
+    /// Every `Dialogue:` line, in the same order subparse returns the entries.
+    pub events: Vec<AssEvent>,
+}
+
+/// A dialogue line ready to be turned into a subtitle.
+pub struct Dialogue {
+    pub style: AssStyle,
+    pub runs: Vec<StyledRun>,
+    pub placement: Placement,
+    pub fade: Option<Fade>,
+}
+
+impl AssScript {
+    /// Looks up a style like VSFilter does: case insensitive, ignoring a
+    /// leading `*`, and falling back to "Default" or the first style.
+    pub fn style(&self, name: &str) -> AssStyle {
+        let name = name.trim().trim_start_matches('*');
+        self.styles
+            .iter()
+            .find(|style| style.name.eq_ignore_ascii_case(name))
+            .or_else(|| {
+                self.styles
+                    .iter()
+                    .find(|style| style.name.eq_ignore_ascii_case("Default"))
+            })
+            .or(self.styles.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The dialogue line at `index`, or a line using the default style.
+    pub fn event(&self, index: usize) -> AssEvent {
+        self.events.get(index).cloned().unwrap_or_default()
+    }
+
+    /// Font size the user selected size corresponds to,
+    /// so only the differences between styles are kept.
+    pub fn reference_font_size(&self) -> f32 {
+        self.style("Default").font_size
+    }
+}
+
+/// Reads `[Script Info]`, the styles section and the style and margins
+/// of every dialogue line from a whole ASS/SSA file.
+pub fn parse_script(data: &str) -> AssScript {
+    let mut section = String::new();
+    let mut info = HashMap::new();
+    let mut style_format: Vec<String> = Vec::new();
+    let mut event_format: Vec<String> = Vec::new();
+    let mut styles = Vec::new();
+    let mut events = Vec::new();
+
+    for line in data.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_ascii_lowercase();
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match (section.as_str(), key.trim()) {
+            ("script info", key) => {
+                info.insert(key.to_ascii_lowercase(), value.to_string());
+            }
+            ("v4+ styles" | "v4 styles", "Format") => style_format = format_fields(value),
+            ("v4+ styles" | "v4 styles", "Style") => {
+                let ssa = section == "v4 styles";
+                let fields = value.splitn(style_format.len().max(1), ',');
+                let fields = style_format
+                    .iter()
+                    .map(String::as_str)
+                    .zip(fields.map(str::trim))
+                    .collect::<HashMap<&str, &str>>();
+                styles.push(parse_style(&fields, ssa));
+            }
+            ("events", "Format") => event_format = format_fields(value),
+            ("events", "Dialogue") => {
+                let fields = value
+                    .splitn(event_format.len().max(1), ',')
+                    .map(str::trim)
+                    .collect::<Vec<&str>>();
+                let field = |name: &str| {
+                    event_format
+                        .iter()
+                        .position(|field| field == name)
+                        .and_then(|index| fields.get(index))
+                        .copied()
+                        .unwrap_or_default()
+                };
+                let margin = |name: &str| field(name).parse::<f32>().unwrap_or(0.0);
+
+                events.push(AssEvent {
+                    style: field("style").to_string(),
+                    margin_l: margin("marginl"),
+                    margin_r: margin("marginr"),
+                    margin_v: margin("marginv"),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let number = |key: &str| info.get(key).and_then(|value| value.parse::<f32>().ok());
+    let (play_res_x, play_res_y) = match (number("playresx"), number("playresy")) {
+        (Some(x), Some(y)) => (x, y),
+        (Some(x), None) => (x, x * 3.0 / 4.0),
+        (None, Some(y)) => (y * 4.0 / 3.0, y),
+        (None, None) => (384.0, 288.0),
+    };
+
+    AssScript {
+        play_res_x,
+        play_res_y,
+        styles,
+        events,
+    }
+}
+
+/// Turns an ASS/SSA dialogue line into styled runs, applying the
+/// `{\...}` override blocks instead of throwing them away.
+pub fn parse_dialogue(input: &str, event: &AssEvent, script: &AssScript) -> Dialogue {
+    let style = script.style(&event.style);
+    let base = style.run_style(style.font_size);
+    let context = TagContext {
+        base: &base,
+        line_style: &style,
+        script,
+    };
+    let mut line = LineOverrides::default();
+    let mut current = base.clone();
+    let mut runs = Vec::new();
+    let mut buffer = String::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut block = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    block.push(c);
+                }
+                if !closed {
+                    // Not an override block, just a stray brace
+                    buffer.push('{');
+                    buffer.push_str(&block);
+                    continue;
+                }
+
+                let mut next = current.clone();
+                let karaoke = line.karaoke;
+                for tag in split_tags(&block) {
+                    apply_tag(tag, &mut next, &mut line, &context);
+                }
+                // Every karaoke tag starts a new syllable, even without a style change
+                if next != current || line.karaoke != karaoke {
+                    push_run(&mut runs, &mut buffer, &current, karaoke);
+                    current = next;
+                }
+            }
+            '\\' => match chars.peek() {
+                Some('N') => {
+                    chars.next();
+                    buffer.push('\n');
+                }
+                Some('n') => {
+                    chars.next();
+                    buffer.push(' ');
+                }
+                Some('h') => {
+                    chars.next();
+                    buffer.push('\u{a0}');
+                }
+                _ => buffer.push('\\'),
+            },
+            '\n' => buffer.push(' '),
+            _ => buffer.push(c),
+        }
+    }
+    push_run(&mut runs, &mut buffer, &current, line.karaoke);
+
+    let pick = |own: f32, fallback: f32| if own > 0.0 { own } else { fallback };
+    let placement = Placement {
+        alignment: line.alignment.unwrap_or(style.alignment),
+        position: line.position,
+        margin_l: pick(event.margin_l, style.margin_l),
+        margin_r: pick(event.margin_r, style.margin_r),
+        margin_v: pick(event.margin_v, style.margin_v),
+        resolution: Size::new(script.play_res_x, script.play_res_y),
+    };
+
+    Dialogue {
+        style,
+        runs,
+        placement,
+        fade: line.fade,
+    }
+}
+
+/// Parses `&HBBGGRR&` and `&HAABBGGRR` colours, ASS stores alpha inverted.
+pub fn parse_color(input: &str) -> Option<Color> {
+    let hex = input.trim().trim_matches('&');
+    let hex = hex
+        .strip_prefix('H')
+        .or_else(|| hex.strip_prefix('h'))
+        .unwrap_or(hex);
+    let value = u32::from_str_radix(hex, 16).ok()?;
+
+    Some(color_from_abgr(value))
+}
+
+/// Style colours are hex in ASS but usually plain decimal numbers in SSA.
+fn parse_style_color(input: &str) -> Option<Color> {
+    let input = input.trim();
+    if input.starts_with(['&', 'H', 'h']) {
+        parse_color(input)
+    } else {
+        input
+            .parse::<i64>()
+            .ok()
+            .map(|value| color_from_abgr(value as u32))
+    }
+}
+
+fn color_from_abgr(value: u32) -> Color {
+    let [blue, green, red, alpha] = [
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+        (value >> 24) as u8,
+    ];
+    Color::from_rgba8(red, green, blue, f32::from(255 - alpha) / 255.0)
+}
+
+/// Lowercased field names of a `Format:` line.
+fn format_fields(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|field| field.trim().to_ascii_lowercase())
+        .collect()
+}
+
+fn parse_style(fields: &HashMap<&str, &str>, ssa: bool) -> AssStyle {
+    let default = AssStyle::default();
+    let text = |key: &str| fields.get(key).copied().unwrap_or_default();
+    let number = |key: &str, fallback: f32| text(key).parse::<f32>().unwrap_or(fallback);
+    // -1 is true, 0 is false
+    let flag = |key: &str| text(key).parse::<i32>().is_ok_and(|value| value != 0);
+    let color = |key: &str, fallback: Color| parse_style_color(text(key)).unwrap_or(fallback);
+
+    let alignment = text("alignment").parse::<u8>().unwrap_or(default.alignment);
+    AssStyle {
+        name: text("name").to_string(),
+        font_name: text("fontname").to_string(),
+        font_size: number("fontsize", default.font_size).max(1.0),
+        primary_color: color("primarycolour", default.primary_color),
+        secondary_color: color("secondarycolour", default.secondary_color),
+        outline_color: if ssa {
+            color("tertiarycolour", default.outline_color)
+        } else {
+            color("outlinecolour", default.outline_color)
+        },
+        back_color: color("backcolour", default.back_color),
+        bold: flag("bold"),
+        italic: flag("italic"),
+        underline: flag("underline"),
+        strikethrough: flag("strikeout"),
+        border_style: text("borderstyle").parse().unwrap_or(default.border_style),
+        outline: number("outline", default.outline),
+        alignment: if ssa {
+            ssa_to_numpad(alignment)
+        } else {
+            alignment
+        },
+        margin_l: number("marginl", default.margin_l),
+        margin_r: number("marginr", default.margin_r),
+        margin_v: number("marginv", default.margin_v),
+    }
+}
+
+/// SSA numbers alignments 1-3 bottom, 5-7 top and 9-11 middle.
+fn ssa_to_numpad(alignment: u8) -> u8 {
+    match alignment {
+        5..=7 => alignment + 2,
+        9..=11 => alignment - 5,
+        1..=3 => alignment,
+        _ => 2,
+    }
+}
+
+fn push_run(
+    runs: &mut Vec<StyledRun>,
+    buffer: &mut String,
+    style: &RunStyle,
+    karaoke: Option<Karaoke>,
+) {
+    if buffer.is_empty() {
+        return;
+    }
+    runs.push(StyledRun {
+        text: std::mem::take(buffer),
+        style: style.clone(),
+        karaoke,
+    });
+}
+
+/// Splits the inside of an override block into tags without the leading
+/// backslash, keeping parenthesised arguments like `\t(\fs20)` in one piece.
+fn split_tags(block: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+
+    for (index, c) in block.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '\\' if depth == 0 => {
+                if let Some(begin) = start {
+                    tags.push(block[begin..index].trim());
+                }
+                start = Some(index + 1);
+            }
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        tags.push(block[begin..].trim());
+    }
+
+    tags
+}
+
+/// State that belongs to the whole line instead of a single run.
+#[derive(Default)]
+struct LineOverrides {
+    /// Only the first `\an`/`\a` counts.
+    alignment: Option<u8>,
+    /// Only the first `\pos`/`\move` counts.
+    position: Option<Position>,
+    /// Only the first `\fad`/`\fade` counts.
+    fade: Option<Fade>,
+    /// Syllable the text currently belongs to.
+    karaoke: Option<Karaoke>,
+    /// Where the next syllable starts, relative to the line start.
+    karaoke_time_ms: u128,
+}
+
+struct TagContext<'a> {
+    base: &'a RunStyle,
+    line_style: &'a AssStyle,
+    script: &'a AssScript,
+}
+
+fn apply_tag(tag: &str, style: &mut RunStyle, line: &mut LineOverrides, context: &TagContext) {
+    let base = context.base;
+    let base_size = context.line_style.font_size;
+
+    // `\fscx`, `\fscy` and `\fsp` are other tags
+    if let Some(arg) = tag.strip_prefix("fs")
+        && !arg.starts_with(|c: char| c.is_ascii_alphabetic())
+    {
+        if arg.is_empty() {
+            style.size = base.size;
+        } else if let Some(delta) = arg.strip_prefix(['+', '-']) {
+            // VSFilter extension: relative size change
+            if let Ok(delta) = delta.parse::<f32>() {
+                let delta = if arg.starts_with('-') { -delta } else { delta };
+                let current = style.size.unwrap_or(1.0) * base_size;
+                style.size = Some((current + delta).max(1.0) / base_size);
+            }
+        } else if let Ok(size) = arg.parse::<f32>() {
+            style.size = Some(size / base_size);
+        }
+        return;
+    }
+    if let Some(arg) = tag.strip_prefix("fn") {
+        style.family = if arg.trim().is_empty() {
+            base.family
+        } else {
+            Some(subtitle::font_family(arg.trim()))
+        };
+        return;
+    }
+    if let Some(arg) = tag.strip_prefix('r') {
+        *style = if arg.trim().is_empty() {
+            base.clone()
+        } else {
+            let other = context.script.style(arg);
+            RunStyle {
+                family: Some(subtitle::font_family(&other.font_name)),
+                ..other.run_style(base_size)
+            }
+        };
+        return;
+    }
+
+    let (name, arg) = if tag.starts_with(['1', '2', '3', '4']) {
+        tag.split_at(2.min(tag.len()))
+    } else {
+        tag.split_at(
+            tag.find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(tag.len()),
+        )
+    };
+
+    match name {
+        "b" => style.bold = parse_flag(arg, base.bold, |weight| weight == 1 || weight >= 600),
+        "i" => style.italic = parse_flag(arg, base.italic, |value| value == 1),
+        "u" => style.underline = parse_flag(arg, base.underline, |value| value == 1),
+        "s" => style.strikethrough = parse_flag(arg, base.strikethrough, |value| value == 1),
+        "c" | "1c" => {
+            style.color = if arg.is_empty() {
+                base.color
+            } else {
+                parse_color(arg).or(style.color)
+            }
+        }
+        "2c" => {
+            style.secondary_color = if arg.is_empty() {
+                base.secondary_color
+            } else {
+                parse_color(arg).or(style.secondary_color)
+            }
+        }
+        "k" | "K" | "kf" | "ko" => {
+            // Durations are in centiseconds
+            if let Ok(duration) = arg.parse::<f32>() {
+                let duration_ms = (duration.max(0.0) * 10.0) as u128;
+                line.karaoke = Some(Karaoke {
+                    start_ms: line.karaoke_time_ms,
+                    duration_ms,
+                    kind: match name {
+                        "K" | "kf" => KaraokeKind::Sweep,
+                        "ko" => KaraokeKind::Outline,
+                        _ => KaraokeKind::Instant,
+                    },
+                });
+                line.karaoke_time_ms += duration_ms;
+            }
+        }
+        "an" => {
+            if let Ok(alignment @ 1..=9) = arg.parse::<u8>() {
+                line.alignment.get_or_insert(alignment);
+            }
+        }
+        "a" => {
+            if let Ok(alignment) = arg.parse::<u8>() {
+                line.alignment.get_or_insert(ssa_to_numpad(alignment));
+            }
+        }
+        "pos" => {
+            if let [x, y] = parse_arguments(arg)[..] {
+                line.position
+                    .get_or_insert(Position::Fixed(Point::new(x, y)));
+            }
+        }
+        "move" => {
+            let (from, to, start, end) = match parse_arguments(arg)[..] {
+                [x1, y1, x2, y2] => ((x1, y1), (x2, y2), 0.0, 0.0),
+                [x1, y1, x2, y2, t1, t2] => ((x1, y1), (x2, y2), t1, t2),
+                _ => return,
+            };
+            line.position.get_or_insert(Position::Moving {
+                from: Point::new(from.0, from.1),
+                to: Point::new(to.0, to.1),
+                start_ms: start.max(0.0) as u128,
+                end_ms: end.max(0.0) as u128,
+            });
+        }
+        "fad" => {
+            if let [fade_in, fade_out] = parse_arguments(arg)[..] {
+                line.fade.get_or_insert(Fade::Simple {
+                    fade_in_ms: fade_in.max(0.0) as u128,
+                    fade_out_ms: fade_out.max(0.0) as u128,
+                });
+            }
+        }
+        "fade" => {
+            if let [a1, a2, a3, t1, t2, t3, t4] = parse_arguments(arg)[..] {
+                line.fade.get_or_insert(Fade::Complex {
+                    alphas: [a1, a2, a3].map(|alpha| alpha.clamp(0.0, 255.0) as u8),
+                    times: [t1, t2, t3, t4].map(|time| time.max(0.0) as u128),
+                });
+            }
+        }
+        // Animations, borders, shadows and the like are not rendered
+        _ => {}
+    }
+}
+
+/// Numbers inside the parentheses of tags like `\pos(10,20)`.
+fn parse_arguments(arg: &str) -> Vec<f32> {
+    arg.trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .filter_map(|value| value.trim().parse::<f32>().ok())
+        .collect()
+}
+
+fn parse_flag(arg: &str, base: bool, is_set: impl Fn(u32) -> bool) -> bool {
+    if arg.is_empty() {
+        return base;
+    }
+    match arg.parse::<u32>() {
+        Ok(value) => is_set(value),
+        Err(_) => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "[Script Info]
+PlayResX: 640
+PlayResY: 480
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, Bold, Italic, Alignment, MarginL, MarginR, MarginV
+Style: Default,Arial,20,&H00FFFFFF,0,0,2,10,10,10
+Style: Sign,Arial,40,&H0000FFFF,-1,0,8,10,10,30
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: 0,0:00:00.00,0:00:01.00,Sign,,0,0,0,,Text
+";
+
+    fn dialogue(input: &str) -> Dialogue {
+        let script = parse_script(SCRIPT);
+        parse_dialogue(input, &script.event(0), &script)
+    }
+
+    #[test]
+    fn colors_are_alpha_blue_green_red() {
+        assert_eq!(
+            parse_color("&H00FF8000&"),
+            Some(Color::from_rgb8(0x00, 0x80, 0xff))
+        );
+        // Alpha is inverted, 0 is opaque
+        assert_eq!(
+            parse_color("&HFF0000FF&"),
+            Some(Color::from_rgba8(0xff, 0, 0, 0.0))
+        );
+        assert_eq!(parse_color("&H00FF&"), Some(Color::from_rgb8(0xff, 0, 0)));
+        assert_eq!(parse_color("&Hgreen&"), None);
+    }
+
+    #[test]
+    fn ssa_alignments_become_numpad() {
+        let numpad = [1, 2, 3, 5, 6, 7, 9, 10, 11, 0].map(ssa_to_numpad);
+        assert_eq!(numpad, [1, 2, 3, 7, 8, 9, 4, 5, 6, 2]);
+    }
+
+    #[test]
+    fn tags_keep_their_parentheses() {
+        assert_eq!(
+            split_tags(r"\b1\t(\fs20\c&HFF&)\pos(1, 2)"),
+            ["b1", r"t(\fs20\c&HFF&)", "pos(1, 2)"]
+        );
+    }
+
+    #[test]
+    fn font_size_tags() {
+        let script = parse_script(SCRIPT);
+        let line_style = script.style("Sign");
+        let base = line_style.run_style(line_style.font_size);
+        let context = TagContext {
+            base: &base,
+            line_style: &line_style,
+            script: &script,
+        };
+        let mut line = LineOverrides::default();
+        let mut size = |tags: &[&str]| {
+            let mut style = base.clone();
+            for tag in tags {
+                apply_tag(tag, &mut style, &mut line, &context);
+            }
+            style.size
+        };
+        assert_eq!(size(&["fs20"]), Some(0.5));
+        assert_eq!(size(&["fs20", "fs+10"]), Some(0.75));
+        assert_eq!(size(&["fs20", "fs"]), None);
+        assert_eq!(size(&["fscx200", "fscy50", "fsp3"]), None);
+    }
+
+    #[test]
+    fn styles_and_inline_overrides() {
+        let dialogue = dialogue(r"Top{\i1}Italic{\r}\NBack{\fs20\c&HFF0000&}Blue");
+        assert_eq!(dialogue.style.name, "Sign");
+        assert_eq!(dialogue.placement.alignment, 8);
+        assert_eq!(dialogue.placement.margin_v, 30.0);
+
+        let yellow = Some(Color::from_rgb8(0xff, 0xff, 0));
+        let runs = dialogue
+            .runs
+            .iter()
+            .map(|run| {
+                let style = &run.style;
+                (
+                    run.text.as_str(),
+                    style.bold,
+                    style.italic,
+                    style.color,
+                    style.size,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            runs,
+            [
+                ("Top", true, false, yellow, None),
+                ("Italic", true, true, yellow, None),
+                ("\nBack", true, false, yellow, None),
+                (
+                    "Blue",
+                    true,
+                    false,
+                    Some(Color::from_rgb8(0, 0, 0xff)),
+                    Some(0.5)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn first_alignment_tag_counts() {
+        assert_eq!(dialogue(r"{\an7}One{\an3}Two").placement.alignment, 7);
+        // SSA style 6 is the top centre
+        assert_eq!(dialogue(r"{\a6}One{\an1}Two").placement.alignment, 8);
+        assert_eq!(dialogue(r"{\an0}One").placement.alignment, 8);
+    }
+}
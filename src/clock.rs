@@ -0,0 +1,220 @@
+use std::time::Instant;
+
+const NANOS_PER_MS: i128 = 1_000_000;
+
+/// The playback position, worked out from the one instant playback was
+/// started or moved at and the position it had then. Adding up the time
+/// between ticks drifts away from the video over a whole film, this does not.
+///
+/// The current instant is passed in rather than read, see the tests.
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    anchor: Instant,
+    /// Position at `anchor` in nanoseconds, before the start when negative.
+    position_ns: i128,
+    playing: bool,
+    /// How fast the position moves, 1.0 is real time.
+    rate: f64,
+}
+
+impl Clock {
+    /// A paused clock at `position_ms`.
+    pub fn new(position_ms: i128, now: Instant) -> Self {
+        Clock {
+            anchor: now,
+            position_ns: position_ms * NANOS_PER_MS,
+            playing: false,
+            rate: 1.0,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Changes the speed from `now` on, the position carries on from where
+    /// it is.
+    pub fn set_rate(&mut self, rate: f64, now: Instant) {
+        self.position_ns = self.position_ns(now);
+        self.anchor = now;
+        self.rate = rate;
+    }
+
+    /// Rounded down, so it goes from -1 to 0 like from 0 to 1.
+    pub fn position_ms(&self, now: Instant) -> i128 {
+        self.position_ns(now).div_euclid(NANOS_PER_MS)
+    }
+
+    pub fn play(&mut self, now: Instant) {
+        if !self.playing {
+            self.anchor = now;
+            self.playing = true;
+        }
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        self.position_ns = self.position_ns(now);
+        self.anchor = now;
+        self.playing = false;
+    }
+
+    pub fn seek(&mut self, position_ms: i128, now: Instant) {
+        self.position_ns = position_ms * NANOS_PER_MS;
+        self.anchor = now;
+    }
+
+    /// Moves the position forward, or back for a negative `by_ms`, without
+    /// losing the part of a millisecond it was at.
+    pub fn skip(&mut self, by_ms: i128, now: Instant) {
+        self.position_ns = self.position_ns(now) + by_ms * NANOS_PER_MS;
+        self.anchor = now;
+    }
+
+    fn position_ns(&self, now: Instant) -> i128 {
+        if self.playing {
+            let elapsed = now.saturating_duration_since(self.anchor);
+            self.position_ns + elapsed.mul_f64(self.rate).as_nanos() as i128
+        } else {
+            self.position_ns
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const TWO_HOURS: Duration = Duration::from_secs(2 * 60 * 60);
+
+    /// Tick intervals around 10 ms that never land on a whole millisecond,
+    /// like the timer of a busy event loop.
+    fn jittery_ticks() -> impl Iterator<Item = Duration> {
+        [10_370, 9_810, 11_050, 10_001, 12_640, 9_999]
+            .into_iter()
+            .map(Duration::from_micros)
+            .cycle()
+    }
+
+    #[test]
+    fn no_drift_over_two_hours_of_ticks() {
+        let start = Instant::now();
+        let mut clock = Clock::new(0, start);
+        clock.play(start);
+
+        let mut now = start;
+        let mut ticks = jittery_ticks();
+        while now - start < TWO_HOURS {
+            now += ticks.next().unwrap();
+            // Reading the position, as every tick does, changes nothing
+            clock.position_ms(now);
+        }
+        assert_eq!(clock.position_ms(now), (now - start).as_millis() as i128);
+    }
+
+    #[test]
+    fn no_drift_over_many_pauses() {
+        let start = Instant::now();
+        let mut clock = Clock::new(0, start);
+        let mut now = start;
+        let mut played = Duration::ZERO;
+        let mut ticks = jittery_ticks();
+        while played < TWO_HOURS {
+            clock.play(now);
+            let playing = ticks.next().unwrap() * 997 / 10;
+            now += playing;
+            played += playing;
+            clock.pause(now);
+            now += ticks.next().unwrap();
+        }
+        assert_eq!(clock.position_ms(now), played.as_millis() as i128);
+    }
+
+    #[test]
+    fn paused_clock_stands_still() {
+        let start = Instant::now();
+        let mut clock = Clock::new(1500, start);
+        assert_eq!(clock.position_ms(start + TWO_HOURS), 1500);
+
+        clock.play(start);
+        clock.pause(start + Duration::from_millis(250));
+        assert!(!clock.is_playing());
+        assert_eq!(clock.position_ms(start + TWO_HOURS), 1750);
+    }
+
+    #[test]
+    fn seeking_moves_the_anchor() {
+        let start = Instant::now();
+        let mut clock = Clock::new(0, start);
+        clock.play(start);
+        clock.seek(60_000, start + Duration::from_secs(10));
+        assert_eq!(clock.position_ms(start + Duration::from_secs(12)), 62_000);
+    }
+
+    #[test]
+    fn no_drift_at_a_faster_rate() {
+        let start = Instant::now();
+        let mut clock = Clock::new(0, start);
+        clock.set_rate(1.25, start);
+        clock.play(start);
+
+        let mut now = start;
+        let mut ticks = jittery_ticks();
+        while now - start < TWO_HOURS {
+            now += ticks.next().unwrap();
+            clock.position_ms(now);
+        }
+        assert_eq!(
+            clock.position_ms(now),
+            (now - start).mul_f64(1.25).as_millis() as i128
+        );
+    }
+
+    #[test]
+    fn changing_the_rate_does_not_jump() {
+        let start = Instant::now();
+        let mut clock = Clock::new(0, start);
+        clock.play(start);
+
+        let changed = start + Duration::from_secs(60);
+        clock.set_rate(2.0, changed);
+        assert_eq!(clock.position_ms(changed), 60_000);
+        assert_eq!(clock.position_ms(changed + Duration::from_secs(10)), 80_000);
+
+        clock.set_rate(0.5, changed + Duration::from_secs(10));
+        assert_eq!(clock.position_ms(changed + Duration::from_secs(20)), 85_000);
+    }
+
+    #[test]
+    fn skipping_keeps_the_part_of_a_millisecond() {
+        let start = Instant::now();
+        let mut clock = Clock::new(0, start);
+        clock.play(start);
+        let mut now = start;
+        for _ in 0..1000 {
+            now += Duration::from_micros(10_600);
+            clock.skip(5000, now);
+            clock.skip(-5000, now);
+        }
+        assert_eq!(clock.position_ms(now), 10_600);
+    }
+
+    #[test]
+    fn going_back_past_the_start() {
+        let start = Instant::now();
+        let mut clock = Clock::new(3000, start);
+        clock.skip(-5000, start);
+        assert_eq!(clock.position_ms(start), -2000);
+
+        clock.play(start);
+        assert_eq!(clock.position_ms(start + Duration::from_micros(500)), -2000);
+        assert_eq!(clock.position_ms(start + Duration::from_millis(1999)), -1);
+        assert_eq!(clock.position_ms(start + Duration::from_millis(2000)), 0);
+        assert_eq!(clock.position_ms(start + Duration::from_millis(2500)), 500);
+    }
+}
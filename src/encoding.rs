@@ -0,0 +1,96 @@
+use std::fmt;
+
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+
+/// How the bytes of a text subtitle file are turned into text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextEncoding {
+    /// Byte order mark, then UTF-16 or UTF-8, then a guess from the content.
+    Auto,
+    Forced(&'static Encoding),
+}
+
+impl TextEncoding {
+    /// Choices offered in the settings, the common ones for subtitles first.
+    pub const ALL: [TextEncoding; 16] = [
+        TextEncoding::Auto,
+        TextEncoding::Forced(encoding_rs::UTF_8),
+        TextEncoding::Forced(encoding_rs::UTF_16LE),
+        TextEncoding::Forced(encoding_rs::UTF_16BE),
+        TextEncoding::Forced(encoding_rs::WINDOWS_1252),
+        TextEncoding::Forced(encoding_rs::WINDOWS_1250),
+        TextEncoding::Forced(encoding_rs::WINDOWS_1251),
+        TextEncoding::Forced(encoding_rs::KOI8_R),
+        TextEncoding::Forced(encoding_rs::WINDOWS_1253),
+        TextEncoding::Forced(encoding_rs::WINDOWS_1254),
+        TextEncoding::Forced(encoding_rs::WINDOWS_1256),
+        TextEncoding::Forced(encoding_rs::SHIFT_JIS),
+        TextEncoding::Forced(encoding_rs::EUC_JP),
+        TextEncoding::Forced(encoding_rs::GBK),
+        TextEncoding::Forced(encoding_rs::BIG5),
+        TextEncoding::Forced(encoding_rs::EUC_KR),
+    ];
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextEncoding::Auto => write!(f, "Auto-detect"),
+            TextEncoding::Forced(encoding) => write!(f, "{}", encoding.name()),
+        }
+    }
+}
+
+/// Decodes a text file, returning the text and the encoding that was used.
+/// Bytes that do not fit the encoding become U+FFFD instead of failing.
+pub fn decode(data: &[u8], encoding: TextEncoding) -> (String, &'static Encoding) {
+    let encoding = match encoding {
+        TextEncoding::Forced(encoding) => encoding,
+        TextEncoding::Auto => detect(data),
+    };
+    let (text, _) = encoding.decode_with_bom_removal(data);
+    (text.into_owned(), encoding)
+}
+
+fn detect(data: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(data) {
+        return encoding;
+    }
+    // Latin text in UTF-16 is also valid UTF-8, full of NUL characters
+    if let Some(encoding) = detect_utf16(data) {
+        return encoding;
+    }
+    if std::str::from_utf8(data).is_ok() {
+        return encoding_rs::UTF_8;
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(data, true);
+    detector.guess(None, true)
+}
+
+/// UTF-16 without a byte order mark, mostly Latin text has a zero byte in
+/// every pair, on the odd bytes for little endian and on the even ones for big.
+fn detect_utf16(data: &[u8]) -> Option<&'static Encoding> {
+    let sample = &data[..data.len().min(4096) & !1];
+    let pairs = sample.len() / 2;
+    if pairs < 4 {
+        return None;
+    }
+
+    let zeros_at = |offset: usize| {
+        sample
+            .chunks_exact(2)
+            .filter(|pair| pair[offset] == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    if odd > pairs * 2 / 5 && even == 0 {
+        Some(encoding_rs::UTF_16LE)
+    } else if even > pairs * 2 / 5 && odd == 0 {
+        Some(encoding_rs::UTF_16BE)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+
+use iced::Color;
+
+use crate::subtitle::{self, RunStyle, StyledRun};
+
+/// Pixel size most players treat as the normal size for SRT text,
+/// used to turn `<font size="..">` into a relative size.
+const REFERENCE_PIXEL_SIZE: f32 = 24.0;
+
+/// Turns SRT/WebVTT style inline markup (`<i>`, `<b>`, `<u>`, `<s>` and
+/// `<font ...>`) into styled runs. Unknown tags are dropped, unclosed tags
+/// run until the end of the line and stray closing tags are ignored.
+pub fn parse_markup(input: &str) -> Vec<StyledRun> {
+    parse_markup_with_classes(input, &HashMap::new())
+}
+
+/// Like [`parse_markup`], also applying `classes` to tags like `<c.name>`.
+/// Classes named after a colour give that colour, as in WebVTT.
+pub fn parse_markup_with_classes(
+    input: &str,
+    classes: &HashMap<String, RunStyle>,
+) -> Vec<StyledRun> {
+    let mut runs = Vec::new();
+    let mut buffer = String::new();
+    // Open tags as (name, whole tag), the style is rebuilt from them so
+    // badly nested closing tags only undo their own tag
+    let mut open_tags: Vec<(String, &str)> = Vec::new();
+    let mut current = RunStyle::default();
+    let mut rest = input;
+
+    while let Some(start) = rest.find('<') {
+        let Some(length) = rest[start..].find('>') else {
+            break;
+        };
+        buffer.push_str(&decode_entities(&rest[..start]));
+        let tag = rest[start + 1..start + length].trim();
+        rest = &rest[start + length + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = tag_name(name);
+            if let Some(index) = open_tags.iter().rposition(|(open, _)| *open == name) {
+                open_tags.remove(index);
+            }
+        } else if !tag.ends_with('/') {
+            open_tags.push((tag_name(tag), tag));
+        }
+
+        let next = open_tags
+            .iter()
+            .fold(RunStyle::default(), |mut style, (name, tag)| {
+                apply_tag(name, tag, &mut style);
+                apply_classes(tag, classes, &mut style);
+                style
+            });
+        if next != current {
+            push_run(&mut runs, &mut buffer, &current);
+            current = next;
+        }
+    }
+    buffer.push_str(&decode_entities(rest));
+    push_run(&mut runs, &mut buffer, &current);
+
+    runs
+}
+
+fn apply_tag(name: &str, tag: &str, style: &mut RunStyle) {
+    match name {
+        "i" => style.italic = true,
+        "b" => style.bold = true,
+        "u" => style.underline = true,
+        "s" => style.strikethrough = true,
+        "font" => apply_font_attributes(tag, style),
+        _ => {}
+    }
+}
+
+fn apply_classes(tag: &str, classes: &HashMap<String, RunStyle>, style: &mut RunStyle) {
+    let name = tag.split_whitespace().next().unwrap_or_default();
+    for class in name.split('.').skip(1) {
+        if let Some(class_style) = classes.get(class) {
+            merge_style(style, class_style);
+        } else if let Some(color) = named_color(class) {
+            style.color = Some(color);
+        }
+    }
+}
+
+/// Adds `other` on top of `style`, anything `other` sets wins.
+pub fn merge_style(style: &mut RunStyle, other: &RunStyle) {
+    style.bold |= other.bold;
+    style.italic |= other.italic;
+    style.underline |= other.underline;
+    style.strikethrough |= other.strikethrough;
+    style.color = other.color.or(style.color);
+    style.size = other.size.or(style.size);
+    style.family = other.family.or(style.family);
+}
+
+/// Replaces the HTML entities subtitle files use with their characters.
+fn decode_entities(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                "lrm" => '\u{200e}',
+                "rlm" => '\u{200f}',
+                _ => {
+                    let number = entity.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse::<u32>().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+
+        match decoded {
+            Some((c, length)) => {
+                output.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// Parses `#rrggbb`, `#rgb`, `rrggbb` and the common named colours.
+pub fn parse_color(input: &str) -> Option<Color> {
+    let input = input.trim();
+    let hex = input.strip_prefix('#').unwrap_or(input);
+
+    if hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        match hex.len() {
+            6 => {
+                let value = u32::from_str_radix(hex, 16).ok()?;
+                return Some(Color::from_rgb8(
+                    (value >> 16) as u8,
+                    (value >> 8) as u8,
+                    value as u8,
+                ));
+            }
+            3 => {
+                let value = u32::from_str_radix(hex, 16).ok()?;
+                let expand = |nibble: u32| (nibble as u8 & 0xf) * 0x11;
+                return Some(Color::from_rgb8(
+                    expand(value >> 8),
+                    expand(value >> 4),
+                    expand(value),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    named_color(input)
+}
+
+/// The basic HTML colour names.
+fn named_color(name: &str) -> Option<Color> {
+    let (red, green, blue) = match name.to_ascii_lowercase().as_str() {
+        "white" => (255, 255, 255),
+        "black" => (0, 0, 0),
+        "red" => (255, 0, 0),
+        "lime" => (0, 255, 0),
+        "green" => (0, 128, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "cyan" | "aqua" => (0, 255, 255),
+        "magenta" | "fuchsia" => (255, 0, 255),
+        "silver" => (192, 192, 192),
+        "gray" | "grey" => (128, 128, 128),
+        "maroon" => (128, 0, 0),
+        "olive" => (128, 128, 0),
+        "navy" => (0, 0, 128),
+        "purple" => (128, 0, 128),
+        "teal" => (0, 128, 128),
+        "orange" => (255, 165, 0),
+        "pink" => (255, 192, 203),
+        _ => return None,
+    };
+    Some(Color::from_rgb8(red, green, blue))
+}
+
+fn push_run(runs: &mut Vec<StyledRun>, buffer: &mut String, style: &RunStyle) {
+    if buffer.is_empty() {
+        return;
+    }
+    runs.push(StyledRun {
+        text: std::mem::take(buffer),
+        style: style.clone(),
+        karaoke: None,
+    });
+}
+
+/// Lowercased tag name, `c.yellow` and `v Bob` both count as their first part.
+fn tag_name(tag: &str) -> String {
+    tag.split(|c: char| c.is_whitespace() || c == '.' || c == '/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+fn apply_font_attributes(tag: &str, style: &mut RunStyle) {
+    for (name, value) in attributes(tag) {
+        match name.as_str() {
+            "color" => style.color = parse_color(&value).or(style.color),
+            "face" => {
+                if let Some(face) = value.split(',').next().map(str::trim)
+                    && !face.is_empty()
+                {
+                    style.family = Some(subtitle::font_family(face));
+                }
+            }
+            "size" => style.size = parse_size(&value).or(style.size),
+            _ => {}
+        }
+    }
+}
+
+/// Sizes 1 to 7 (and `+n`/`-n`) are the old HTML size steps, anything
+/// larger is taken as pixels.
+fn parse_size(value: &str) -> Option<f32> {
+    const STEPS: [f32; 7] = [0.6, 0.8, 1.0, 1.2, 1.5, 2.0, 3.0];
+
+    let value = value.trim();
+    let step = if let Some(delta) = value.strip_prefix('+') {
+        3 + delta.parse::<i32>().ok()?
+    } else if let Some(delta) = value.strip_prefix('-') {
+        3 - delta.parse::<i32>().ok()?
+    } else {
+        let size = value.trim_end_matches("px").parse::<f32>().ok()?;
+        if size > 7.0 {
+            return Some(size / REFERENCE_PIXEL_SIZE);
+        }
+        size as i32
+    };
+
+    Some(STEPS[(step.clamp(1, 7) - 1) as usize])
+}
+
+/// Splits `font color="red" face='Arial' size=3` into name/value pairs.
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let mut rest = tag
+        .split_once(char::is_whitespace)
+        .map_or("", |(_, attrs)| attrs);
+
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals].trim().to_ascii_lowercase();
+        let after = rest[equals + 1..].trim_start();
+
+        let (value, remaining) = match after.chars().next() {
+            Some(quote @ ('"' | '\'')) => match after[1..].find(quote) {
+                Some(end) => (&after[1..end + 1], &after[end + 2..]),
+                None => (&after[1..], ""),
+            },
+            _ => match after.find(char::is_whitespace) {
+                Some(end) => (&after[..end], &after[end..]),
+                None => (after, ""),
+            },
+        };
+
+        result.push((name, value.to_string()));
+        rest = remaining;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(input: &str) -> Vec<(String, RunStyle)> {
+        parse_markup(input)
+            .into_iter()
+            .map(|run| (run.text, run.style))
+            .collect()
+    }
+
+    fn text(text: &str, style: RunStyle) -> (String, RunStyle) {
+        (String::from(text), style)
+    }
+
+    const BOLD: RunStyle = RunStyle {
+        bold: true,
+        italic: false,
+        underline: false,
+        strikethrough: false,
+        color: None,
+        secondary_color: None,
+        size: None,
+        family: None,
+    };
+
+    #[test]
+    fn nested_tags() {
+        assert_eq!(
+            runs("<b>Bold <i>both</i></b> plain"),
+            [
+                text("Bold ", BOLD),
+                text(
+                    "both",
+                    RunStyle {
+                        italic: true,
+                        ..BOLD
+                    }
+                ),
+                text(" plain", RunStyle::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_and_mismatched_tags() {
+        // Closing the outer tag first only ends that tag
+        assert_eq!(
+            runs("<b><i>Both</b> italic</i>"),
+            [
+                text(
+                    "Both",
+                    RunStyle {
+                        italic: true,
+                        ..BOLD
+                    }
+                ),
+                text(
+                    " italic",
+                    RunStyle {
+                        italic: true,
+                        ..RunStyle::default()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(runs("<b>To the end"), [text("To the end", BOLD)]);
+        assert_eq!(
+            runs("Stray</u> <blink>tags"),
+            [text("Stray tags", RunStyle::default())]
+        );
+        assert_eq!(runs("1 < 2"), [text("1 < 2", RunStyle::default())]);
+    }
+
+    #[test]
+    fn font_colors() {
+        let color = |input: &str| parse_markup(input)[0].style.color;
+        assert_eq!(
+            color("<font color=\"#ff8000\">Orange</font>"),
+            Some(Color::from_rgb8(0xff, 0x80, 0))
+        );
+        assert_eq!(
+            color("<font color='#0f0'>Lime</font>"),
+            Some(Color::from_rgb8(0, 0xff, 0))
+        );
+        assert_eq!(
+            color("<FONT COLOR=Yellow>Yellow</FONT>"),
+            Some(Color::from_rgb8(0xff, 0xff, 0))
+        );
+        assert_eq!(color("<font color=\"nope\">Plain</font>"), None);
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(
+            parse_color("#102030"),
+            Some(Color::from_rgb8(0x10, 0x20, 0x30))
+        );
+        assert_eq!(
+            parse_color("102030"),
+            Some(Color::from_rgb8(0x10, 0x20, 0x30))
+        );
+        assert_eq!(parse_color("#fff"), Some(Color::WHITE));
+        assert_eq!(parse_color("Grey"), Some(Color::from_rgb8(128, 128, 128)));
+        assert_eq!(parse_color("#12345"), None);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("3"), Some(1.0));
+        assert_eq!(parse_size("+2"), Some(1.5));
+        assert_eq!(parse_size("-5"), Some(0.6));
+        assert_eq!(parse_size("48px"), Some(2.0));
+        assert_eq!(parse_size("big"), None);
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            parse_markup("&lt;b&gt; &amp;amp; &#65;&#x42; &unknown; &")[0].text,
+            "<b> &amp; AB &unknown; &"
+        );
+    }
+
+    #[test]
+    fn classes_and_color_names() {
+        let classes = HashMap::from([(String::from("loud"), BOLD)]);
+        let runs = parse_markup_with_classes("<c.loud.red>Shout</c> <c.blue>Blue</c>", &classes);
+        assert_eq!(
+            runs[0].style,
+            RunStyle {
+                color: Some(Color::from_rgb8(255, 0, 0)),
+                ..BOLD
+            }
+        );
+        assert_eq!(runs[2].style.color, Some(Color::from_rgb8(0, 0, 255)));
+    }
+
+    #[test]
+    fn merged_styles_keep_what_is_not_set() {
+        let mut style = RunStyle {
+            italic: true,
+            color: Some(Color::WHITE),
+            size: Some(2.0),
+            ..RunStyle::default()
+        };
+        merge_style(
+            &mut style,
+            &RunStyle {
+                color: Some(Color::BLACK),
+                ..BOLD
+            },
+        );
+        assert_eq!(
+            style,
+            RunStyle {
+                bold: true,
+                italic: true,
+                color: Some(Color::BLACK),
+                size: Some(2.0),
+                ..RunStyle::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,527 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use encoding_rs::Encoding;
+use failure::Fail;
+use subparse::{
+    SubtitleFormat,
+    errors::{idx_errors, mdvd_errors, srt_errors, ssa_errors},
+    get_subtitle_format,
+};
+
+use crate::{
+    archive, ass,
+    encoding::{self, TextEncoding},
+    html, mkv, mp4, pgs,
+    srt::{self, Repair},
+    subtitle::{StyledRun, Subtitle},
+    subviewer, ttml,
+    video::{self, Codec, Video},
+    vobsub, webvtt,
+};
+
+/// Extensions offered in the file picker, videos are there for their
+/// subtitle tracks and ZIP archives for the subtitle files in them.
+pub const SUBTITLE_EXTENSIONS: [&str; 19] = [
+    "ass", "ssa", "srt", "vtt", "sub", "idx", "sup", "ttml", "dfxp", "xml", "mkv", "mka", "mks",
+    "webm", "mp4", "m4v", "mov", "3gp", "zip",
+];
+
+/// Files that are listed from an archive or a folder.
+const ENTRY_EXTENSIONS: [&str; 10] = [
+    "ass", "ssa", "srt", "vtt", "sub", "idx", "sup", "ttml", "dfxp", "xml",
+];
+
+/// Videos with subtitle tracks, see [`open_video`].
+const VIDEO_EXTENSIONS: [&str; 8] = ["mkv", "mka", "mks", "webm", "mp4", "m4v", "mov", "3gp"];
+
+/// Settings that change how a file is parsed.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadOptions {
+    /// Turns frame numbers into times for frame based formats like MicroDVD.
+    pub fps: f64,
+    pub encoding: TextEncoding,
+}
+
+/// The subtitles of a file, with what had to be fixed to read them.
+pub struct Loaded {
+    pub subtitles: Vec<Subtitle>,
+    /// Only filled when a broken SRT file was read by the lenient reader.
+    pub repairs: Vec<Repair>,
+    /// What the text was decoded as, `None` for subtitles that are not text.
+    pub encoding: Option<&'static Encoding>,
+}
+
+/// Why a subtitle file could not be loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// The file, or the VobSub file that belongs to it, could not be read.
+    Read {
+        path: PathBuf,
+        cause: String,
+    },
+    UnknownFormat,
+    /// The file is in a known format but broken, `line` counts from 1.
+    Parse {
+        line: Option<usize>,
+        cause: String,
+    },
+    NoSubtitles,
+    /// A video's subtitle track in a codec that can not be shown, like PGS.
+    UnsupportedCodec(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Read { path, cause } => {
+                write!(f, "could not read {}: {cause}", path.display())
+            }
+            LoadError::UnknownFormat => write!(f, "not a subtitle format this app knows"),
+            LoadError::Parse {
+                line: Some(line),
+                cause,
+            } => write!(f, "line {line}: {cause}"),
+            LoadError::Parse { line: None, cause } => write!(f, "{cause}"),
+            LoadError::NoSubtitles => write!(f, "the file has no subtitles in it"),
+            LoadError::UnsupportedCodec(codec) => {
+                write!(f, "{codec} subtitle tracks can not be shown")
+            }
+        }
+    }
+}
+
+impl LoadError {
+    pub fn read(path: &Path, error: std::io::Error) -> Self {
+        LoadError::Read {
+            path: path.to_path_buf(),
+            cause: error.to_string(),
+        }
+    }
+
+    /// Uses the innermost message, the outer subparse errors only say
+    /// that parsing failed and at which (zero based) line.
+    fn subparse(error: subparse::errors::Error) -> Self {
+        let cause = std::iter::successors(Some(&error as &dyn Fail), |fail| fail.cause())
+            .last()
+            .map_or_else(|| error.to_string(), ToString::to_string);
+        LoadError::Parse {
+            line: subparse_line(&error).map(|line| line + 1),
+            cause,
+        }
+    }
+}
+
+/// Zero based line of a subparse error, for the formats that report one.
+fn subparse_line(error: &subparse::errors::Error) -> Option<usize> {
+    let cause = error.cause()?;
+    if let Some(error) = cause.downcast_ref::<srt_errors::Error>() {
+        let srt_errors::ErrorKind::ErrorAtLine { line_num } = error.kind() else {
+            return None;
+        };
+        return Some(*line_num);
+    }
+    if let Some(error) = cause.downcast_ref::<ssa_errors::Error>() {
+        use ssa_errors::ErrorKind::*;
+        return match error.kind() {
+            SsaFieldsInfoNotFound => None,
+            SsaMissingField { line_num, .. }
+            | SsaDuplicateField { line_num, .. }
+            | SsaTextFieldNotLast { line_num }
+            | SsaIncorrectNumberOfFields { line_num }
+            | SsaWrongTimepointFormat { line_num, .. }
+            | SsaDialogLineParseError { line_num, .. }
+            | SsaLineParseError { line_num, .. } => Some(*line_num),
+        };
+    }
+    if let Some(error) = cause.downcast_ref::<mdvd_errors::Error>() {
+        let mdvd_errors::ErrorKind::ErrorAtLine { line_num } = error.kind() else {
+            return None;
+        };
+        return Some(*line_num);
+    }
+    if let Some(error) = cause.downcast_ref::<idx_errors::Error>() {
+        let idx_errors::ErrorKind::IdxLineParseError { line_num, .. } = error.kind();
+        return Some(*line_num);
+    }
+    None
+}
+
+/// Parses a subtitle file, picking the parser from the extension and content.
+/// VobSub needs both the `.idx` and `.sub`, the other one is read from
+/// next to `path`.
+///
+/// Fonts and fades that depend on the user's settings are left at their
+/// defaults, see `IcedSubtitleWatcher::apply_settings`.
+pub fn load(path: &Path, data: &[u8], options: LoadOptions) -> Result<Loaded, LoadError> {
+    let read = |path: &Path| std::fs::read(path).map_err(|error| LoadError::read(path, error));
+    let loaded = load_any(path, data, options, read)?;
+    if loaded.subtitles.is_empty() {
+        return Err(LoadError::NoSubtitles);
+    }
+    Ok(loaded)
+}
+
+/// Videos are read with [`open_video`] instead of [`load`].
+pub fn is_video(path: &Path) -> bool {
+    VIDEO_EXTENSIONS
+        .iter()
+        .any(|extension| has_extension(path, extension))
+}
+
+/// ZIP archives are read with [`open_archive`] instead of [`load`].
+pub fn is_archive(path: &Path) -> bool {
+    has_extension(path, "zip")
+}
+
+/// Lists the subtitle files in a ZIP archive, see [`load_entry`]. A VobSub
+/// `.sub` is left out when its `.idx` is there, they are picked as one.
+pub fn open_archive(path: &Path) -> Result<Vec<String>, LoadError> {
+    let entries = archive::entries(path).map_err(|error| LoadError::read(path, error))?;
+    let listed = entries
+        .iter()
+        .filter(|name| {
+            is_listed(Path::new(name), |other| {
+                entries.contains(&other.to_string_lossy().into_owned())
+            })
+        })
+        .cloned()
+        .collect::<Vec<String>>();
+    if listed.is_empty() {
+        return Err(LoadError::NoSubtitles);
+    }
+    Ok(listed)
+}
+
+/// Lists the subtitle files in a folder, for a playlist of episodes.
+/// VobSub pairs are listed once, by their `.idx`.
+pub fn open_folder(path: &Path) -> Result<Vec<PathBuf>, LoadError> {
+    let listed = std::fs::read_dir(path)
+        .map_err(|error| LoadError::read(path, error))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|file| file.is_file() && is_listed(file, Path::exists))
+        .collect::<Vec<PathBuf>>();
+    if listed.is_empty() {
+        return Err(LoadError::NoSubtitles);
+    }
+    Ok(listed)
+}
+
+/// Whether a subtitle file is listed for an archive or folder, `exists`
+/// looks for the other files next to it.
+fn is_listed(file: &Path, exists: impl Fn(&Path) -> bool) -> bool {
+    ENTRY_EXTENSIONS
+        .iter()
+        .any(|extension| has_extension(file, extension))
+        && !(has_extension(file, "sub") && exists(&file.with_extension("idx")))
+}
+
+/// Parses one file of a ZIP archive straight from memory, like [`load`]
+/// would parse it on disk. The other half of a VobSub pair comes from the
+/// archive as well.
+pub fn load_entry(path: &Path, name: &str, options: LoadOptions) -> Result<Loaded, LoadError> {
+    let read = |entry: &Path| {
+        let entry = entry.to_string_lossy();
+        archive::read_entry(path, &entry).map_err(|error| LoadError::Read {
+            path: path.join(&*entry),
+            cause: error.to_string(),
+        })
+    };
+    let data = read(Path::new(name))?;
+    let loaded = load_any(Path::new(name), &data, options, read)?;
+    if loaded.subtitles.is_empty() {
+        return Err(LoadError::NoSubtitles);
+    }
+    Ok(loaded)
+}
+
+/// Reads the subtitle tracks of a video, see [`load_track`].
+pub fn open_video(path: &Path) -> Result<Video, LoadError> {
+    let video = if ["mkv", "mka", "mks", "webm"]
+        .iter()
+        .any(|extension| has_extension(path, extension))
+    {
+        mkv::read(path)
+    } else {
+        mp4::read(path)
+    }
+    .map_err(|error| LoadError::read(path, error))?;
+    if video.tracks.is_empty() {
+        return Err(LoadError::NoSubtitles);
+    }
+    Ok(video)
+}
+
+/// Parses one subtitle track of a video. The blocks are put back together
+/// into a file of the track's format and parsed like one.
+pub fn load_track(video: &Video, number: u64, options: LoadOptions) -> Result<Loaded, LoadError> {
+    let track = video.track(number).ok_or(LoadError::NoSubtitles)?;
+    let blocks = video.blocks(number);
+    let subtitles = |subtitles| Loaded {
+        subtitles,
+        repairs: Vec::new(),
+        encoding: None,
+    };
+    let loaded = match &track.codec {
+        Codec::Srt => subtitles(
+            blocks
+                .iter()
+                .map(|block| {
+                    let text = String::from_utf8_lossy(&block.data);
+                    text_subtitle(block.start_time_ms, block.end_time_ms, &text)
+                })
+                .collect(),
+        ),
+        Codec::Ass => load_text(
+            Path::new("track.ass"),
+            &video::ass_script(track, blocks),
+            options.fps,
+        )?,
+        Codec::Ssa => load_text(
+            Path::new("track.ssa"),
+            &video::ass_script(track, blocks),
+            options.fps,
+        )?,
+        Codec::WebVtt => subtitles(webvtt::parse(&video::webvtt_text(track, blocks))),
+        Codec::TimedText => subtitles(
+            blocks
+                .iter()
+                .filter(|block| !block.data.is_empty())
+                .map(|block| Subtitle {
+                    runs: vec![StyledRun::plain(String::from_utf8_lossy(&block.data))],
+                    ..Subtitle::new(block.start_time_ms, block.end_time_ms)
+                })
+                .collect(),
+        ),
+        Codec::Other(codec) => return Err(LoadError::UnsupportedCodec(codec.clone())),
+    };
+    if loaded.subtitles.is_empty() {
+        return Err(LoadError::NoSubtitles);
+    }
+    Ok(loaded)
+}
+
+/// `read` fetches the file that goes with a VobSub `.idx` or `.sub`.
+fn load_any(
+    path: &Path,
+    data: &[u8],
+    options: LoadOptions,
+    read: impl Fn(&Path) -> Result<Vec<u8>, LoadError>,
+) -> Result<Loaded, LoadError> {
+    let subtitles = |subtitles| Loaded {
+        subtitles,
+        repairs: Vec::new(),
+        encoding: None,
+    };
+    if pgs::is_pgs(data) {
+        return Ok(subtitles(pgs::parse(data)));
+    }
+    if vobsub::is_vobsub(data) {
+        let idx = read(&path.with_extension("idx"))?;
+        let (idx, _) = encoding::decode(&idx, options.encoding);
+        return Ok(subtitles(vobsub::parse(&idx, data)));
+    }
+
+    let (text, encoding) = encoding::decode(data, options.encoding);
+    if has_extension(path, "idx") {
+        let sub = read(&path.with_extension("sub"))?;
+        return Ok(subtitles(vobsub::parse(&text, &sub)));
+    }
+    let loaded = load_text(path, &text, options.fps)?;
+    Ok(Loaded {
+        encoding: Some(encoding),
+        ..loaded
+    })
+}
+
+fn load_text(path: &Path, data: &str, fps: f64) -> Result<Loaded, LoadError> {
+    let subtitles = |subtitles| Loaded {
+        subtitles,
+        repairs: Vec::new(),
+        encoding: None,
+    };
+    if is_webvtt(path, data) {
+        return Ok(subtitles(webvtt::parse(data)));
+    }
+    if ["ttml", "dfxp", "xml"]
+        .iter()
+        .any(|extension| has_extension(path, extension))
+        || ttml::is_ttml(data)
+    {
+        return ttml::parse(data)
+            .map(subtitles)
+            .map_err(|error| LoadError::Parse {
+                line: Some(error.pos().row as usize),
+                cause: error.to_string(),
+            });
+    }
+
+    // subparse only knows lowercase extensions
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_ascii_lowercase());
+    let format = get_subtitle_format(extension.as_deref(), data.as_bytes())
+        .ok_or(LoadError::UnknownFormat)?;
+    if format == SubtitleFormat::MicroDVD && subviewer::is_subviewer(data) {
+        return Ok(subtitles(subviewer::parse(data)));
+    }
+    // subparse reads `-00:00:01,000` as a second after the start
+    let negative = format == SubtitleFormat::SubRip && srt::has_negative_times(data);
+    let subtitle_file = match subparse::parse_str(format, data, fps) {
+        Ok(subtitle_file) if !negative => subtitle_file,
+        Err(error) if format != SubtitleFormat::SubRip => {
+            return Err(LoadError::subparse(error));
+        }
+        _ => {
+            let (cues, repairs) = srt::parse(data);
+            let subtitles = cues
+                .into_iter()
+                .map(|cue| text_subtitle(cue.start_time_ms, cue.end_time_ms, &cue.text))
+                .collect();
+            return Ok(Loaded {
+                subtitles,
+                repairs,
+                encoding: None,
+            });
+        }
+    };
+
+    let script = (format == SubtitleFormat::SubStationAlpha).then(|| ass::parse_script(data));
+
+    let entries = subtitle_file
+        .get_subtitle_entries()
+        .map_err(LoadError::subparse)?
+        .iter()
+        .enumerate()
+        .filter(|(_, subtitle_item)| {
+            // MicroDVD files often start with `{1}{1}23.976`, the frame rate
+            // they were made for, which is not meant to be shown
+            format != SubtitleFormat::MicroDVD
+                || subtitle_item.timespan.start != subtitle_item.timespan.end
+                || subtitle_item
+                    .line
+                    .as_ref()
+                    .is_none_or(|line| line.trim().parse::<f64>().is_err())
+        })
+        .map(|(index, subtitle_item)| {
+            let start_time_ms = i128::from(subtitle_item.timespan.start.msecs());
+            let end_time_ms = i128::from(subtitle_item.timespan.end.msecs());
+
+            match (&subtitle_item.line, &script) {
+                (Some(sub_content), Some(script)) => {
+                    let dialogue = ass::parse_dialogue(sub_content, &script.event(index), script);
+                    Subtitle {
+                        runs: dialogue.runs,
+                        scale: dialogue.style.font_size / script.reference_font_size(),
+                        style: Some(dialogue.style),
+                        placement: Some(dialogue.placement),
+                        fade: dialogue.fade,
+                        ..Subtitle::new(start_time_ms, end_time_ms)
+                    }
+                }
+                (Some(sub_content), None) => text_subtitle(start_time_ms, end_time_ms, sub_content),
+                (None, _) => Subtitle {
+                    runs: vec![StyledRun::plain("... [No Sub]")],
+                    ..Subtitle::new(start_time_ms, end_time_ms)
+                },
+            }
+        })
+        .collect::<Vec<Subtitle>>();
+
+    Ok(subtitles(entries))
+}
+
+/// A subtitle from SRT style text, which can have HTML like tags and
+/// leftovers from ASS conversions.
+fn text_subtitle(start_time_ms: i128, end_time_ms: i128, text: &str) -> Subtitle {
+    // Strip {} left over from ASS conversions, <> becomes styling
+    let mut subtitle = strip_tags(text, '{', '}');
+    subtitle = subtitle.replace("\\N", "\n");
+    Subtitle {
+        runs: html::parse_markup(&subtitle),
+        ..Subtitle::new(start_time_ms, end_time_ms)
+    }
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|path_extension| path_extension.eq_ignore_ascii_case(extension))
+}
+
+fn is_webvtt(path: &Path, data: &str) -> bool {
+    has_extension(path, "vtt") || data.trim_start_matches('\u{feff}').starts_with("WEBVTT")
+}
+
+fn strip_tags(input: &str, delim_start: char, delim_end: char) -> String {
+    let mut output = String::new();
+    let mut count: i64 = 0;
+    for c in input.chars() {
+        if c == delim_start {
+            count += 1;
+            continue;
+        } else if c == delim_end {
+            count -= 1;
+            continue;
+        }
+        if count == 0 {
+            if c == '\n' {
+                output.push(' ');
+            } else {
+                output.push(c);
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_micro_dvd(data: &str, fps: f64) -> Vec<Subtitle> {
+        let options = LoadOptions {
+            fps,
+            encoding: TextEncoding::Auto,
+        };
+        load(Path::new("film.sub"), data.as_bytes(), options)
+            .unwrap()
+            .subtitles
+    }
+
+    #[test]
+    fn negative_srt_times_load_as_negative() {
+        let options = LoadOptions {
+            fps: 25.0,
+            encoding: TextEncoding::Auto,
+        };
+        let data = "1\n-00:00:01,000 --> 00:00:02,000\nEarly\n";
+        let loaded = load(Path::new("film.srt"), data.as_bytes(), options).unwrap();
+        let subtitle = &loaded.subtitles[0];
+        assert_eq!(
+            (subtitle.start_time_ms, subtitle.end_time_ms),
+            (-1000, 2000)
+        );
+    }
+
+    #[test]
+    fn micro_dvd_frame_rate_header_is_not_shown() {
+        let subtitles = load_micro_dvd("{1}{1}23.976\n{25}{50}Hello\n{75}{100}World\n", 25.0);
+        let times = subtitles
+            .iter()
+            .map(|subtitle| (subtitle.start_time_ms, subtitle.end_time_ms))
+            .collect::<Vec<(i128, i128)>>();
+        assert_eq!(times, [(1000, 2000), (3000, 4000)]);
+        assert_eq!(subtitles[0].plain_text(), "Hello");
+    }
+
+    #[test]
+    fn micro_dvd_frames_use_the_frame_rate() {
+        let subtitles = load_micro_dvd("{1}{1}23.976\n{24}{48}Hello\n", 24.0);
+        assert_eq!(
+            (subtitles[0].start_time_ms, subtitles[0].end_time_ms),
+            (1000, 2000)
+        );
+    }
+}
@@ -1,18 +1,66 @@
-use std::time::{Duration, Instant};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
+use args::Args;
+use clock::Clock;
 use dafont::FcFontCache;
+use encoding::TextEncoding;
+use encoding_rs::Encoding;
 use iced::{
-    Alignment, Color, Element, Font, Length, Subscription, Task, Theme,
+    Alignment, Color, Element, Event, Font, Length, Subscription, Task, Theme,
+    alignment::Vertical,
     daemon::Appearance,
-    keyboard,
+    event, keyboard,
     widget::{
-        Column, button, column, container, pick_list, rich_text, row, scrollable, span, text,
-        text_input, tooltip,
+        button, column, container, pick_list, responsive, row, scrollable, text, text_input,
+        tooltip,
     },
 };
-use subparse::get_subtitle_format;
+use loader::{LoadError, LoadOptions, Loaded};
+use playlist::{Episode, Playlist};
+use srt::Repair;
+use subtitle::{Fade, Subtitle};
+use time::{OffsetDateTime, UtcOffset, macros::format_description};
+use video::{Track, Video};
+
+mod archive;
+mod args;
+mod ass;
+mod clock;
+mod encoding;
+mod html;
+mod loader;
+mod mkv;
+mod mp4;
+mod pgs;
+mod playlist;
+mod srt;
+mod subtitle;
+mod subviewer;
+mod ttml;
+mod video;
+mod vobsub;
+mod watcher;
+mod webvtt;
 
 fn main() -> iced::Result {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}\n\n{}", args::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", args::USAGE);
+        return Ok(());
+    }
+    // Only reliable while the program has a single thread
+    let utc_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+
     iced::application(
         IcedSubtitleWatcher::title(),
         IcedSubtitleWatcher::update,
@@ -30,7 +78,7 @@ fn main() -> iced::Result {
         },
         text_color: b.palette().text,
     })
-    .run_with(IcedSubtitleWatcher::new)
+    .run_with(move || IcedSubtitleWatcher::new(args, utc_offset))
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +90,14 @@ enum Message {
     PlaybackTimeEdited(String),
     OffsetEdited(String),
     LoadFileButtonPressed,
+    FileDropped(PathBuf),
+    FileChanged(PathBuf),
+    WatchFailed(String),
+    TrackSelected(u64),
+    EntryPicked(String),
+    EntryPickerCancelled,
+    EntrySelected(String),
+    EpisodeSelected(usize),
     TabPressed,
     ToggleTransparency,
     KeySpacePressed,
@@ -49,23 +105,298 @@ enum Message {
     DecreaseFontSize,
     ThemeSelected(Theme),
     SubFontChanged(String),
+    DefaultFadeSelected(u128),
+    FrameRateSelected(FrameRate),
+    ConvertFromSelected(FrameRate),
+    ConvertToSelected(FrameRate),
+    FpsPresetSelected(FpsPreset),
+    ResyncMarkPressed,
+    ResyncMarkCancelled,
+    ResyncUndo,
+    ResyncReset,
+    CustomFrameRateEdited(String),
+    EncodingSelected(TextEncoding),
+    DismissLoadError,
+    ToggleRepairDetails,
+    DismissRepairs,
+    SecondaryLoadPressed,
+    SecondaryCleared,
+    SecondaryOffsetEdited(String),
+    SecondaryFontChanged(String),
+    SecondaryFontSizeSelected(u16),
+    SecondaryColorSelected(TextColor),
+    DualLayoutSelected(DualLayout),
     ReverseBackPressed,
     FastForwardPressed,
+    SlowerPressed,
+    FasterPressed,
+    RateReset,
 }
+
+/// Playback speeds to keep up with a player that is not at normal speed.
+const MIN_RATE: f64 = 0.25;
+const MAX_RATE: f64 = 4.0;
+const RATE_STEP: f64 = 0.25;
+
 enum Tab {
     Main,
     Settings,
 }
 
+/// Frame rate used for formats that time subtitles in frames (MicroDVD).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameRate {
+    Film,
+    Fps24,
+    Pal,
+    Ntsc,
+    Fps30,
+    Custom,
+}
+
+impl FrameRate {
+    const ALL: [FrameRate; 6] = [
+        FrameRate::Film,
+        FrameRate::Fps24,
+        FrameRate::Pal,
+        FrameRate::Ntsc,
+        FrameRate::Fps30,
+        FrameRate::Custom,
+    ];
+    /// The rates a video can be converted between.
+    const FIXED: [FrameRate; 5] = [
+        FrameRate::Film,
+        FrameRate::Fps24,
+        FrameRate::Pal,
+        FrameRate::Ntsc,
+        FrameRate::Fps30,
+    ];
+
+    /// Frames per second, `None` for a custom rate.
+    fn fps(&self) -> Option<f64> {
+        match self {
+            FrameRate::Film => Some(24000.0 / 1001.0),
+            FrameRate::Fps24 => Some(24.0),
+            FrameRate::Pal => Some(25.0),
+            FrameRate::Ntsc => Some(30000.0 / 1001.0),
+            FrameRate::Fps30 => Some(30.0),
+            FrameRate::Custom => None,
+        }
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameRate::Film => write!(f, "23.976"),
+            FrameRate::Fps24 => write!(f, "24"),
+            FrameRate::Pal => write!(f, "25"),
+            FrameRate::Ntsc => write!(f, "29.97"),
+            FrameRate::Fps30 => write!(f, "30"),
+            FrameRate::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Common frame rate mix-ups, subtitles timed for a video at the first
+/// rate shown with one at the second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FpsPreset {
+    PalToFilm,
+    FilmToPal,
+    PalToNtsc,
+    NtscToPal,
+    Fps24ToFilm,
+    FilmToFps24,
+}
+
+impl FpsPreset {
+    const ALL: [FpsPreset; 6] = [
+        FpsPreset::PalToFilm,
+        FpsPreset::FilmToPal,
+        FpsPreset::PalToNtsc,
+        FpsPreset::NtscToPal,
+        FpsPreset::Fps24ToFilm,
+        FpsPreset::FilmToFps24,
+    ];
+
+    fn rates(&self) -> (FrameRate, FrameRate) {
+        match self {
+            FpsPreset::PalToFilm => (FrameRate::Pal, FrameRate::Film),
+            FpsPreset::FilmToPal => (FrameRate::Film, FrameRate::Pal),
+            FpsPreset::PalToNtsc => (FrameRate::Pal, FrameRate::Ntsc),
+            FpsPreset::NtscToPal => (FrameRate::Ntsc, FrameRate::Pal),
+            FpsPreset::Fps24ToFilm => (FrameRate::Fps24, FrameRate::Film),
+            FpsPreset::FilmToFps24 => (FrameRate::Film, FrameRate::Fps24),
+        }
+    }
+}
+
+impl fmt::Display for FpsPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (from, to) = self.rates();
+        match self {
+            FpsPreset::PalToFilm | FpsPreset::FilmToPal => write!(f, "{from} → {to} (PAL/film)"),
+            FpsPreset::PalToNtsc | FpsPreset::NtscToPal => write!(f, "{from} → {to} (PAL/NTSC)"),
+            FpsPreset::Fps24ToFilm | FpsPreset::FilmToFps24 => {
+                write!(f, "{from} → {to} (24/film)")
+            }
+        }
+    }
+}
+
+/// Where the second subtitles go next to the main ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DualLayout {
+    /// Main subtitles right above the second ones, in the middle.
+    Stacked,
+    /// Main subtitles at the top of the window, the second ones at the bottom.
+    TopBottom,
+    SideBySide,
+}
+
+impl DualLayout {
+    const ALL: [DualLayout; 3] = [
+        DualLayout::Stacked,
+        DualLayout::TopBottom,
+        DualLayout::SideBySide,
+    ];
+}
+
+impl fmt::Display for DualLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DualLayout::Stacked => write!(f, "Stacked in the middle"),
+            DualLayout::TopBottom => write!(f, "Top and bottom of the window"),
+            DualLayout::SideBySide => write!(f, "Side by side"),
+        }
+    }
+}
+
+/// Text colour of the second subtitles, so they are easy to tell apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextColor {
+    Theme,
+    White,
+    Yellow,
+    Cyan,
+    Green,
+    Pink,
+}
+
+impl TextColor {
+    const ALL: [TextColor; 6] = [
+        TextColor::Theme,
+        TextColor::White,
+        TextColor::Yellow,
+        TextColor::Cyan,
+        TextColor::Green,
+        TextColor::Pink,
+    ];
+
+    /// `None` for the theme's text colour.
+    fn color(&self) -> Option<Color> {
+        match self {
+            TextColor::Theme => None,
+            TextColor::White => Some(Color::WHITE),
+            TextColor::Yellow => Some(Color::from_rgb8(255, 235, 59)),
+            TextColor::Cyan => Some(Color::from_rgb8(77, 208, 225)),
+            TextColor::Green => Some(Color::from_rgb8(129, 199, 132)),
+            TextColor::Pink => Some(Color::from_rgb8(240, 98, 146)),
+        }
+    }
+}
+
+impl fmt::Display for TextColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextColor::Theme => write!(f, "Theme"),
+            TextColor::White => write!(f, "White"),
+            TextColor::Yellow => write!(f, "Yellow"),
+            TextColor::Cyan => write!(f, "Cyan"),
+            TextColor::Green => write!(f, "Green"),
+            TextColor::Pink => write!(f, "Pink"),
+        }
+    }
+}
+
+/// Times of subtitles that drift away from the video: every time is
+/// multiplied by `scale`, then `shift_ms` is added.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Resync {
+    scale: f64,
+    shift_ms: i128,
+}
+
+impl Resync {
+    /// The change that moves two subtitles, starting at `from`, to start
+    /// at `to`. `None` when the subtitles are the same or would swap places.
+    fn from_points(from: (i128, i128), to: (i128, i128)) -> Option<Resync> {
+        let from = (from.0 as f64, from.1 as f64);
+        let to = (to.0 as f64, to.1 as f64);
+        let scale = (to.1 - to.0) / (from.1 - from.0);
+        (scale.is_finite() && scale > 0.0).then(|| Resync {
+            scale,
+            shift_ms: (to.0 - from.0 * scale).round() as i128,
+        })
+    }
+
+    /// `self` followed by `next`.
+    fn then(self, next: Resync) -> Resync {
+        Resync {
+            scale: self.scale * next.scale,
+            shift_ms: (self.shift_ms as f64 * next.scale).round() as i128 + next.shift_ms,
+        }
+    }
+}
+
+impl fmt::Display for Resync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "times × {:.5}, then {}",
+            self.scale,
+            Timing::from_ms(self.shift_ms).to_string_formatted()
+        )
+    }
+}
+
+/// A second subtitle file shown together with the main one, like a
+/// translation, with its own timing and look.
+struct Secondary {
+    subtitles: Vec<Subtitle>,
+    file: Option<PathBuf>,
+    offset_str: String,
+    /// Added to the times of the second subtitles, negative shows them earlier.
+    offset_time: i128,
+    font: String,
+    font_size: u16,
+    color: TextColor,
+    layout: DualLayout,
+}
+
+impl Default for Secondary {
+    fn default() -> Self {
+        Secondary {
+            subtitles: Vec::new(),
+            file: None,
+            offset_str: String::from("00:00:00:000"),
+            offset_time: 0,
+            font: String::new(),
+            font_size: 36,
+            color: TextColor::Theme,
+            layout: DualLayout::Stacked,
+        }
+    }
+}
+
 struct IcedSubtitleWatcher {
     offset_str: String,
-    offset_time: u128,
+    offset_time: i128,
     playback_time_str: String,
-    playback_time: u128,
-    play: bool,
-    time_head: Instant,
-    time_before: Duration,
-    time_after: Duration,
+    /// Where the clock was at the last tick, what is on screen.
+    playback_time: i128,
+    clock: Clock,
     active_subtitles: Vec<Subtitle>,
     tab: Tab,
     transparent: bool,
@@ -73,6 +404,52 @@ struct IcedSubtitleWatcher {
     active_theme: Theme,
     available_font: Vec<String>,
     active_sub_font: String,
+    /// Fade in/out for subtitles that do not bring their own, 0 is off.
+    default_fade_ms: u128,
+    frame_rate: FrameRate,
+    custom_frame_rate_str: String,
+    custom_frame_rate: f64,
+    /// Frame rate the subtitles were timed for and the one of the video,
+    /// the times are stretched when they differ.
+    convert_from: FrameRate,
+    convert_to: FrameRate,
+    /// When the last subtitle of the loaded file ends before stretching.
+    source_length_ms: u128,
+    /// Applied to the loaded file after the frame rate conversion.
+    resync: Option<Resync>,
+    /// What `resync` was before each change, for undo.
+    resync_undo: Vec<Option<Resync>>,
+    /// The first of the two subtitles marked for a resync, when it starts
+    /// and when it should, with its text.
+    resync_mark: Option<(i128, i128, String)>,
+    /// Why the last resync could not be done.
+    resync_error: Option<String>,
+    encoding: TextEncoding,
+    /// What the loaded file was read as, shown next to the choice.
+    detected_encoding: Option<&'static Encoding>,
+    /// Kept so the file can be parsed again when a setting changes.
+    loaded_file: Option<PathBuf>,
+    /// Name of the file that failed to load and why, shown until dismissed.
+    load_error: Option<(String, LoadError)>,
+    /// Why the loaded file is not reloaded when it changes.
+    watch_error: Option<String>,
+    /// What the lenient SRT reader fixed or left out in the loaded file.
+    repairs: Vec<Repair>,
+    show_repair_details: bool,
+    /// When the loaded file was last parsed again after changing on disk.
+    last_reload: Option<OffsetDateTime>,
+    /// The subtitle tracks when the loaded file is a video.
+    video: Option<Video>,
+    selected_track: Option<u64>,
+    /// The subtitle files when the loaded file is a ZIP archive.
+    archive_entries: Vec<String>,
+    selected_entry: Option<String>,
+    /// An archive waiting for one of its subtitle files to be picked.
+    entry_picker: Option<(PathBuf, Vec<String>)>,
+    /// Episodes that play one after another, when several files were opened.
+    playlist: Option<Playlist>,
+    secondary: Secondary,
+    utc_offset: UtcOffset,
 }
 
 impl IcedSubtitleWatcher {
@@ -84,48 +461,66 @@ impl IcedSubtitleWatcher {
         self.active_theme.clone()
     }
 
-    fn new() -> (Self, Task<Message>) {
+    fn new(args: Args, utc_offset: UtcOffset) -> (Self, Task<Message>) {
         let font_cache = FcFontCache::build();
         let fonts = font_cache.list();
 
-        (
-            Self {
-                offset_str: String::from("00:00:00:000"),
-                offset_time: 0,
-                playback_time_str: String::from("00:00:00:000"),
-                playback_time: 0,
-                play: false,
-                time_head: Instant::now(),
-                time_before: Duration::from_micros(0),
-                time_after: Duration::from_secs(0),
-                active_subtitles: Vec::new(),
-                tab: Tab::Main,
-                transparent: false,
-                font_size: 48,
-                active_theme: Theme::Dark,
-                available_font: fonts
-                    .iter()
-                    .filter(|(font_content, _)| font_content.name.is_some())
-                    .map(|(ok_font, _)| ok_font.name.clone().unwrap())
-                    .collect::<Vec<String>>(),
-                active_sub_font: String::new(),
-            },
-            Task::none(),
-        )
+        let offset_time = args.offset_ms.unwrap_or(0);
+        let playback_time = args.start_at_ms.unwrap_or(0);
+        let mut watcher = Self {
+            offset_str: Timing::from_ms(offset_time).to_string_formatted(),
+            offset_time,
+            playback_time_str: Timing::from_ms(playback_time + offset_time).to_string_formatted(),
+            playback_time,
+            clock: Clock::new(playback_time, Instant::now()),
+            active_subtitles: Vec::new(),
+            tab: Tab::Main,
+            transparent: false,
+            font_size: args.font_size.unwrap_or(48),
+            active_theme: args.theme.unwrap_or(Theme::Dark),
+            available_font: fonts
+                .iter()
+                .filter(|(font_content, _)| font_content.name.is_some())
+                .map(|(ok_font, _)| ok_font.name.clone().unwrap())
+                .collect::<Vec<String>>(),
+            active_sub_font: String::new(),
+            default_fade_ms: 0,
+            frame_rate: FrameRate::Pal,
+            custom_frame_rate_str: String::from("25"),
+            custom_frame_rate: 25.0,
+            convert_from: FrameRate::Pal,
+            convert_to: FrameRate::Pal,
+            source_length_ms: 0,
+            resync: None,
+            resync_undo: Vec::new(),
+            resync_mark: None,
+            resync_error: None,
+            encoding: TextEncoding::Auto,
+            detected_encoding: None,
+            loaded_file: None,
+            load_error: None,
+            watch_error: None,
+            repairs: Vec::new(),
+            show_repair_details: false,
+            last_reload: None,
+            video: None,
+            selected_track: None,
+            archive_entries: Vec::new(),
+            selected_entry: None,
+            entry_picker: None,
+            playlist: None,
+            secondary: Secondary::default(),
+            utc_offset,
+        };
+        watcher.open_files(args.files);
+
+        (watcher, Task::none())
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Tick => {
-                self.time_after = self.time_head.elapsed();
-
-                self.playback_time += self.time_after.as_millis() - self.time_before.as_millis();
-
-                self.time_before = self.time_after;
-
-                self.playback_time_str =
-                    Timing::from_u128_ms(self.playback_time + self.offset_time)
-                        .to_string_formatted();
+                self.show_position();
                 Task::none()
             }
             Message::TabPressed => {
@@ -140,7 +535,7 @@ impl IcedSubtitleWatcher {
                 Task::none()
             }
             Message::KeySpacePressed => {
-                if self.play {
+                if self.clock.is_playing() {
                     Task::done(()).map(|_| Message::PauseButtonPressed)
                 } else {
                     Task::done(()).map(|_| Message::PlayButtonPressed)
@@ -152,51 +547,187 @@ impl IcedSubtitleWatcher {
             }
             Message::SubFontChanged(font_string) => {
                 self.active_sub_font = font_string;
-                self.active_subtitles = self
+                let font = Font {
+                    family: iced::font::Family::Name(subtitle::font_family(&self.active_sub_font)),
+                    ..Default::default()
+                };
+                for item in self.active_subtitles.iter_mut() {
+                    item.font = font;
+                }
+                Task::none()
+            }
+            Message::DefaultFadeSelected(fade_ms) => {
+                self.default_fade_ms = fade_ms;
+                let fade = self.default_fade();
+                let all = self
                     .active_subtitles
                     .iter_mut()
-                    .map(|item| Subtitle {
-                        start_time_ms: item.start_time_ms,
-                        end_time_ms: item.end_time_ms,
-                        text: item.text.clone(),
-                        font: Font {
-                            family: iced::font::Family::Name(Box::leak(
-                                self.active_sub_font.clone().into_boxed_str(),
-                            )),
-                            ..Default::default()
-                        },
-                    })
-                    .collect::<Vec<Subtitle>>();
+                    .chain(self.secondary.subtitles.iter_mut());
+                for item in all {
+                    if item.style.is_none() {
+                        item.fade = fade;
+                    }
+                }
+                Task::none()
+            }
+            Message::FrameRateSelected(frame_rate) => {
+                self.frame_rate = frame_rate;
+                self.reload_file();
+                Task::none()
+            }
+            Message::ConvertFromSelected(frame_rate) => {
+                self.convert_from = frame_rate;
+                self.reload_file();
+                self.reload_secondary_file();
+                Task::none()
+            }
+            Message::ConvertToSelected(frame_rate) => {
+                self.convert_to = frame_rate;
+                self.reload_file();
+                self.reload_secondary_file();
+                Task::none()
+            }
+            Message::FpsPresetSelected(preset) => {
+                (self.convert_from, self.convert_to) = preset.rates();
+                self.reload_file();
+                self.reload_secondary_file();
+                Task::none()
+            }
+            Message::ResyncMarkPressed => {
+                self.mark_resync();
+                Task::none()
+            }
+            Message::ResyncMarkCancelled => {
+                self.resync_mark = None;
+                self.resync_error = None;
+                Task::none()
+            }
+            Message::ResyncUndo => {
+                if let Some(resync) = self.resync_undo.pop() {
+                    self.resync = resync;
+                    self.reload_file();
+                }
+                Task::none()
+            }
+            Message::ResyncReset => {
+                if self.resync.is_some() {
+                    self.resync_undo.push(self.resync.take());
+                    self.reload_file();
+                }
+                Task::none()
+            }
+            Message::CustomFrameRateEdited(fps_content) => {
+                if let Ok(fps) = fps_content.trim().parse::<f64>()
+                    && fps > 0.0
+                {
+                    self.custom_frame_rate = fps;
+                    self.reload_file();
+                }
+                self.custom_frame_rate_str = fps_content;
+                Task::none()
+            }
+            Message::EncodingSelected(encoding) => {
+                self.encoding = encoding;
+                self.reload_file();
+                Task::none()
+            }
+            Message::DismissLoadError => {
+                self.load_error = None;
+                self.watch_error = None;
+                Task::none()
+            }
+            Message::ToggleRepairDetails => {
+                self.show_repair_details = !self.show_repair_details;
+                Task::none()
+            }
+            Message::DismissRepairs => {
+                self.repairs.clear();
+                self.show_repair_details = false;
+                Task::none()
+            }
+            Message::SecondaryLoadPressed => {
+                let picked_file = rfd::FileDialog::new()
+                    .set_title("Choose a second subtitle file...")
+                    .add_filter("Subtitle file", &loader::SUBTITLE_EXTENSIONS)
+                    .pick_file();
+
+                if let Some(picked_file) = picked_file {
+                    self.load_secondary_file(picked_file);
+                }
+                Task::none()
+            }
+            Message::SecondaryCleared => {
+                self.secondary.subtitles.clear();
+                self.secondary.file = None;
+                Task::none()
+            }
+            Message::SecondaryOffsetEdited(time_content) => {
+                if let Some(offset_time) = Timing::from_string_fmtd(time_content.clone()) {
+                    self.secondary.offset_time = offset_time.to_ms();
+                    self.secondary.offset_str = time_content;
+                }
+                Task::none()
+            }
+            Message::SecondaryFontChanged(font_string) => {
+                self.secondary.font = font_string;
+                let font = Font {
+                    family: iced::font::Family::Name(subtitle::font_family(&self.secondary.font)),
+                    ..Default::default()
+                };
+                for item in self.secondary.subtitles.iter_mut() {
+                    item.font = font;
+                }
+                Task::none()
+            }
+            Message::SecondaryFontSizeSelected(font_size) => {
+                self.secondary.font_size = font_size;
+                Task::none()
+            }
+            Message::SecondaryColorSelected(color) => {
+                self.secondary.color = color;
+                for item in self.secondary.subtitles.iter_mut() {
+                    item.color = color.color();
+                }
+                Task::none()
+            }
+            Message::DualLayoutSelected(layout) => {
+                self.secondary.layout = layout;
                 Task::none()
             }
             Message::PlayButtonPressed => {
-                self.time_head = Instant::now();
-                self.time_before = self.time_head.elapsed();
-                self.play = true;
+                self.clock.play(Instant::now());
                 Task::none()
             }
             Message::PauseButtonPressed => {
-                self.play = false;
+                self.clock.pause(Instant::now());
+                self.show_position();
                 Task::none()
             }
             Message::FastForwardPressed => {
-                self.playback_time = self.playback_time.saturating_add(5000);
-                self.playback_time_str =
-                    Timing::from_u128_ms(self.playback_time + self.offset_time)
-                        .to_string_formatted();
+                self.clock.skip(5000, Instant::now());
+                self.show_position();
                 Task::none()
             }
             Message::ReverseBackPressed => {
-                self.playback_time = self.playback_time.saturating_sub(5000);
-                self.playback_time_str =
-                    Timing::from_u128_ms(self.playback_time + self.offset_time)
-                        .to_string_formatted();
+                self.clock.skip(-5000, Instant::now());
+                self.show_position();
+                Task::none()
+            }
+            Message::SlowerPressed => {
+                self.set_rate(self.clock.rate() - RATE_STEP);
+                Task::none()
+            }
+            Message::FasterPressed => {
+                self.set_rate(self.clock.rate() + RATE_STEP);
+                Task::none()
+            }
+            Message::RateReset => {
+                self.set_rate(1.0);
                 Task::none()
             }
             Message::ResetTimeHeadPressed => {
-                self.playback_time = 0;
-                self.playback_time_str =
-                    Timing::from_u128_ms(self.offset_time).to_string_formatted();
+                self.clock.seek(0, Instant::now());
+                self.show_position();
                 Task::none()
             }
             Message::IncreaseFontSize => {
@@ -208,107 +739,119 @@ impl IcedSubtitleWatcher {
             }
             Message::DecreaseFontSize => {
                 self.font_size = self.font_size.saturating_sub(1);
-                if self.font_size <= 0 {
+                if self.font_size == 0 {
                     self.font_size += 1;
                 }
                 Task::none()
             }
             Message::OffsetEdited(time_content) => {
                 if let Some(ok_time) = Timing::from_string_fmtd(time_content.clone()) {
-                    self.offset_time = ok_time.to_u128_ms();
+                    self.offset_time = ok_time.to_ms();
                     self.offset_str = time_content;
-                    self.playback_time_str =
-                        Timing::from_u128_ms(self.playback_time + self.offset_time)
-                            .to_string_formatted();
+                    if let Some(playlist) = &mut self.playlist {
+                        playlist.current_mut().offset_ms = Some(self.offset_time);
+                    }
+                    self.playback_time_str = Timing::from_ms(self.playback_time + self.offset_time)
+                        .to_string_formatted();
                 }
                 Task::none()
             }
             Message::PlaybackTimeEdited(time_content) => {
                 if let Some(ok_time) = Timing::from_string_fmtd(time_content.clone()) {
-                    self.playback_time = ok_time.to_u128_ms();
+                    // The seek field shows the time with the offset
+                    self.playback_time = ok_time.to_ms() - self.offset_time;
+                    self.clock.seek(self.playback_time, Instant::now());
                     self.playback_time_str = time_content;
                 }
                 Task::none()
             }
             Message::LoadFileButtonPressed => {
-                let picked_file = rfd::FileDialog::new()
-                    .set_title("Choose a subtitle file...")
-                    .add_filter("Subtitle file", &["ass", "srt"])
-                    .pick_file();
+                let picked_files = rfd::FileDialog::new()
+                    .set_title("Choose a subtitle file, or several for a playlist...")
+                    .add_filter("Subtitle file", &loader::SUBTITLE_EXTENSIONS)
+                    .pick_files();
 
-                if let None = picked_file {
-                    println!("Failed to pick file!");
-                } else {
-                    let picked_file = picked_file.unwrap().clone();
-                    let data = std::fs::read_to_string(picked_file.clone());
-
-                    if let Err(_) = data {
-                        return Task::none();
+                // Nothing was picked when the dialog was cancelled
+                if let Some(picked_files) = picked_files {
+                    self.open_files(picked_files);
+                }
+                Task::none()
+            }
+            Message::FileDropped(path) => {
+                self.open_files(vec![path]);
+                Task::none()
+            }
+            Message::TrackSelected(number) => {
+                self.load_track(number);
+                Task::none()
+            }
+            Message::EntryPicked(name) => {
+                if let Some((path, entries)) = self.entry_picker.take() {
+                    self.load_entry(path, entries, name);
+                }
+                Task::none()
+            }
+            Message::EntryPickerCancelled => {
+                self.entry_picker = None;
+                Task::none()
+            }
+            Message::EntrySelected(name) => {
+                if let Some(path) = self.loaded_file.clone() {
+                    self.load_entry(path, self.archive_entries.clone(), name);
+                }
+                Task::none()
+            }
+            Message::EpisodeSelected(index) => {
+                if let Some(playlist) = &self.playlist {
+                    let offset_time = playlist.offset_ms(index);
+                    self.play_episode(index, offset_time);
+                }
+                Task::none()
+            }
+            Message::FileChanged(path) => {
+                if self.loaded_file.as_ref() == Some(&path) {
+                    self.load_file(path);
+                    if self.load_error.is_none() {
+                        self.last_reload =
+                            Some(OffsetDateTime::now_utc().to_offset(self.utc_offset));
                     }
-                    let data = data.unwrap();
-
-                    let format =
-                        get_subtitle_format(picked_file.extension(), data.as_bytes()).unwrap();
-                    let subtitle_file = subparse::parse_str(format, &data, 25.0).unwrap();
-
-                    self.active_subtitles = subtitle_file
-                        .get_subtitle_entries()
-                        .unwrap()
-                        .iter()
-                        .map(|subtitle_item| {
-                            let sub_content_option = subtitle_item.line.clone();
-                            let sanitised_sub = if let Some(sub_content) = sub_content_option {
-                                // Strip <> and {}
-                                // Future: Get font and header data, italics maybe from the stripped data.
-                                let mut subtitle = strip_tags(&sub_content, '<', '>');
-                                subtitle = strip_tags(&subtitle, '{', '}');
-                                subtitle = subtitle.replace("\\N", "\n");
-                                subtitle
-                            } else {
-                                "... [No Sub]".to_string()
-                            };
-
-                            Subtitle {
-                                start_time_ms: subtitle_item.timespan.start.msecs() as u128,
-                                end_time_ms: subtitle_item.timespan.end.msecs() as u128,
-                                text: sanitised_sub,
-                                font: Font {
-                                    family: iced::font::Family::Name(Box::leak(
-                                        self.active_sub_font.clone().into_boxed_str(),
-                                    )),
-                                    ..Default::default()
-                                },
-                            }
-                        })
-                        .collect::<Vec<Subtitle>>();
                 }
                 Task::none()
             }
+            Message::WatchFailed(error) => {
+                self.watch_error = Some(error);
+                Task::none()
+            }
         }
     }
 
     fn view(&self) -> Element<'_, Message> {
         let content_up = if !self.transparent {
             let play_button = tooltip(
-                better_button("▷", 16, self.play, Message::PlayButtonPressed),
+                better_button("▷", 16, self.clock.is_playing(), Message::PlayButtonPressed),
                 "Play",
                 tooltip::Position::Bottom,
             );
             let pause_button = tooltip(
-                better_button("■", 16, !self.play, Message::PauseButtonPressed),
+                better_button(
+                    "■",
+                    16,
+                    !self.clock.is_playing(),
+                    Message::PauseButtonPressed,
+                ),
                 "Pause",
                 tooltip::Position::Bottom,
             );
 
             let offset_input = text_input(&self.offset_str, &self.offset_str)
-                .on_input_maybe(match self.play {
+                .on_input_maybe(match self.clock.is_playing() {
                     true => None,
                     false => Some(Message::OffsetEdited),
                 })
                 .width(Length::Fixed(130.0));
 
             let player_input = text_input(&self.playback_time_str, &self.playback_time_str)
-                .on_input_maybe(match self.play {
+                .on_input_maybe(match self.clock.is_playing() {
                     true => None,
                     false => Some(Message::PlaybackTimeEdited),
                 })
@@ -331,7 +874,12 @@ impl IcedSubtitleWatcher {
             );
 
             let reset_button = tooltip(
-                better_button("⟲", 16, self.play, Message::ResetTimeHeadPressed),
+                better_button(
+                    "⟲",
+                    16,
+                    self.clock.is_playing(),
+                    Message::ResetTimeHeadPressed,
+                ),
                 "Reset playback to start",
                 tooltip::Position::Bottom,
             );
@@ -349,11 +897,57 @@ impl IcedSubtitleWatcher {
             );
 
             let file_picker = tooltip(
-                better_button("🗁", 16, self.play, Message::LoadFileButtonPressed),
+                better_button(
+                    "🗁",
+                    16,
+                    self.clock.is_playing(),
+                    Message::LoadFileButtonPressed,
+                ),
                 "Open subtitle file",
                 tooltip::Position::Bottom,
             );
 
+            let slower_button = tooltip(
+                button(text_size_ccff_container("«", 16))
+                    .on_press_maybe(
+                        (self.clock.rate() > MIN_RATE).then_some(Message::SlowerPressed),
+                    )
+                    .width(Length::Fixed(35.0)),
+                "Play slower ([)",
+                tooltip::Position::Bottom,
+            );
+
+            let rate_button = tooltip(
+                button(text_size_ccff_container(
+                    format!("{}x", self.clock.rate()),
+                    16,
+                ))
+                .on_press(Message::RateReset)
+                .style(button::text),
+                "Playback speed, click for normal speed (Backspace)",
+                tooltip::Position::Bottom,
+            );
+
+            let faster_button = tooltip(
+                button(text_size_ccff_container("»", 16))
+                    .on_press_maybe(
+                        (self.clock.rate() < MAX_RATE).then_some(Message::FasterPressed),
+                    )
+                    .width(Length::Fixed(35.0)),
+                "Play faster (])",
+                tooltip::Position::Bottom,
+            );
+
+            let resync_button = tooltip(
+                button(text_size_ccff_container("⚑", 16))
+                    .on_press_maybe(
+                        (!self.active_subtitles.is_empty()).then_some(Message::ResyncMarkPressed),
+                    )
+                    .width(Length::Fixed(35.0)),
+                "Resync: the nearest line should show now, mark two far apart (M)",
+                tooltip::Position::Bottom,
+            );
+
             let increase_font = tooltip(
                 button(text_size_ccff_container("+", 16))
                     .on_press(Message::IncreaseFontSize)
@@ -387,6 +981,8 @@ impl IcedSubtitleWatcher {
                     .align_y(Alignment::Center),
                     ff_button,
                     reset_button,
+                    row![slower_button, rate_button, faster_button].align_y(Alignment::Center),
+                    resync_button,
                     file_picker,
                     settings_button,
                     increase_font,
@@ -396,6 +992,19 @@ impl IcedSubtitleWatcher {
                         .align_y(Alignment::Center),
                     decrease_font,
                 ]
+                .push_maybe(self.last_reload.map(|reloaded| {
+                    let reloaded = reloaded
+                        .format(format_description!("[hour]:[minute]:[second]"))
+                        .unwrap_or_default();
+                    tooltip(
+                        text_size_ccff_container(format!("⟳ {reloaded}"), 16)
+                            .width(Length::Shrink)
+                            .height(Length::Fill)
+                            .align_y(Alignment::Center),
+                        "Reloaded after the file changed on disk",
+                        tooltip::Position::Bottom,
+                    )
+                }))
                 .spacing(15),
             )
             .align_x(Alignment::Center)
@@ -407,25 +1016,43 @@ impl IcedSubtitleWatcher {
 
         let output: Element<'_, Message> = match self.tab {
             Tab::Main => {
-                // New
-                let subs_to_diplay = self
-                    .active_subtitles
-                    .iter()
-                    .filter(|subtitle| {
-                        (self.playback_time >= subtitle.start_time_ms)
-                            && (self.playback_time <= subtitle.end_time_ms)
-                    })
-                    .collect::<Vec<&Subtitle>>();
+                let playback_time = self.playback_time;
+                let main = |unplaced_y| {
+                    subtitle_area(
+                        &self.active_subtitles,
+                        playback_time,
+                        self.font_size,
+                        unplaced_y,
+                    )
+                };
+                let second = |unplaced_y| {
+                    subtitle_area(
+                        &self.secondary.subtitles,
+                        playback_time - self.secondary.offset_time,
+                        self.secondary.font_size,
+                        unplaced_y,
+                    )
+                };
 
-                let sub_content = subs_to_diplay.iter().fold(
-                    Column::new().spacing(10).align_x(Alignment::Center),
-                    |mut accu, sub| {
-                        accu = accu.push(sub.view(self.font_size));
-                        accu
-                    },
-                );
-
-                sub_content.into()
+                if self.secondary.subtitles.is_empty() {
+                    main(Vertical::Center)
+                } else {
+                    match self.secondary.layout {
+                        DualLayout::Stacked => {
+                            column![main(Vertical::Bottom), second(Vertical::Top)]
+                                .spacing(10)
+                                .into()
+                        }
+                        DualLayout::TopBottom => {
+                            column![main(Vertical::Top), second(Vertical::Bottom)].into()
+                        }
+                        DualLayout::SideBySide => {
+                            row![main(Vertical::Center), second(Vertical::Center)]
+                                .spacing(10)
+                                .into()
+                        }
+                    }
+                }
             }
             Tab::Settings => container(scrollable(
                 column![
@@ -446,6 +1073,173 @@ impl IcedSubtitleWatcher {
                         )
                         .width(350)
                     ]
+                    .spacing(10),
+                    row![
+                        text("Default fade in/out (ms)").width(200),
+                        pick_list(
+                            [0, 100, 150, 200, 300, 500, 1000],
+                            Some(self.default_fade_ms),
+                            Message::DefaultFadeSelected
+                        )
+                        .width(350),
+                        text("For every format but ASS and SSA, which fade on their own"),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                    row![
+                        text("Frame rate (MicroDVD)").width(200),
+                        pick_list(
+                            FrameRate::ALL,
+                            Some(self.frame_rate),
+                            Message::FrameRateSelected
+                        )
+                        .width(350)
+                    ]
+                    .push_maybe((self.frame_rate == FrameRate::Custom).then(|| {
+                        text_input("fps", &self.custom_frame_rate_str)
+                            .on_input(Message::CustomFrameRateEdited)
+                            .width(Length::Fixed(100.0))
+                    }))
+                    .spacing(10),
+                    row![
+                        text("Convert frame rate").width(200),
+                        pick_list(
+                            FrameRate::FIXED,
+                            Some(self.convert_from),
+                            Message::ConvertFromSelected
+                        )
+                        .width(100),
+                        text("→"),
+                        pick_list(
+                            FrameRate::FIXED,
+                            Some(self.convert_to),
+                            Message::ConvertToSelected
+                        )
+                        .width(100),
+                        pick_list(
+                            FpsPreset::ALL,
+                            FpsPreset::ALL.into_iter().find(|preset| {
+                                preset.rates() == (self.convert_from, self.convert_to)
+                            }),
+                            Message::FpsPresetSelected
+                        )
+                        .placeholder("Presets")
+                        .width(250),
+                    ]
+                    .push_maybe(
+                        (self.fps_factor() != 1.0 && !self.active_subtitles.is_empty()).then(
+                            || {
+                                text(format!(
+                                    "Ends at {} instead of {}",
+                                    Timing::from_ms(
+                                        playlist::length_ms(&self.active_subtitles) as i128
+                                    )
+                                    .to_string_formatted(),
+                                    Timing::from_ms(self.source_length_ms as i128)
+                                        .to_string_formatted()
+                                ))
+                            }
+                        )
+                    )
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                    row![
+                        text("Resync").width(200),
+                        text(self.resync.map_or_else(
+                            || String::from("None, mark two lines with ⚑ while playing"),
+                            |resync| resync.to_string()
+                        ))
+                        .width(350),
+                        button(text("Undo")).on_press_maybe(
+                            (!self.resync_undo.is_empty()).then_some(Message::ResyncUndo)
+                        ),
+                        button(text("Reset"))
+                            .on_press_maybe(self.resync.is_some().then_some(Message::ResyncReset)),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                    row![
+                        text("Text encoding").width(200),
+                        pick_list(
+                            TextEncoding::ALL,
+                            Some(self.encoding),
+                            Message::EncodingSelected
+                        )
+                        .width(350)
+                    ]
+                    .push_maybe(
+                        self.detected_encoding
+                            .map(|encoding| text(format!("Read as {}", encoding.name())))
+                    )
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                    row![
+                        text("Second subtitle file").width(200),
+                        button(text("Open...")).on_press(Message::SecondaryLoadPressed),
+                        text(self.secondary.file.as_ref().map_or_else(
+                            || String::from("None"),
+                            |file| file.display().to_string()
+                        ))
+                        .width(Length::Fill),
+                        button(text_size_ccff_container("✕", 16))
+                            .on_press_maybe(
+                                self.secondary
+                                    .file
+                                    .is_some()
+                                    .then_some(Message::SecondaryCleared)
+                            )
+                            .width(Length::Fixed(35.0)),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                    row![
+                        text("Second subtitle offset").width(200),
+                        text_input(&self.secondary.offset_str, &self.secondary.offset_str)
+                            .on_input(Message::SecondaryOffsetEdited)
+                            .width(Length::Fixed(130.0)),
+                        text("A - in front shows them earlier"),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                    row![
+                        text("Second subtitle font").width(200),
+                        pick_list(
+                            self.available_font.clone(),
+                            Some(self.secondary.font.clone()),
+                            Message::SecondaryFontChanged
+                        )
+                        .width(350)
+                    ]
+                    .spacing(10),
+                    row![
+                        text("Second subtitle size").width(200),
+                        pick_list(
+                            [16, 20, 24, 28, 32, 36, 42, 48, 56, 64],
+                            Some(self.secondary.font_size),
+                            Message::SecondaryFontSizeSelected
+                        )
+                        .width(350)
+                    ]
+                    .spacing(10),
+                    row![
+                        text("Second subtitle colour").width(200),
+                        pick_list(
+                            TextColor::ALL,
+                            Some(self.secondary.color),
+                            Message::SecondaryColorSelected
+                        )
+                        .width(350)
+                    ]
+                    .spacing(10),
+                    row![
+                        text("Two subtitles layout").width(200),
+                        pick_list(
+                            DualLayout::ALL,
+                            Some(self.secondary.layout),
+                            Message::DualLayoutSelected
+                        )
+                        .width(350)
+                    ]
                     .spacing(10)
                 ]
                 .spacing(10),
@@ -453,23 +1247,591 @@ impl IcedSubtitleWatcher {
             .into(),
         };
 
-        let full_output: Element<'_, Message> = container(
-            column![
-                content_up,
-                container(output)
-                    .align_x(Alignment::Center)
-                    .align_y(Alignment::Center)
-                    .height(Length::Fill)
-                    .width(Length::Fill)
+        let resync_status = (!self.transparent)
+            .then(|| match (&self.resync_mark, &self.resync_error) {
+                (Some((_, should_start, line)), _) => Some(format!(
+                    "Resync: \"{line}\" marked at {}, mark a second line later on",
+                    Timing::from_ms(should_start + self.offset_time).to_string_formatted()
+                )),
+                (None, Some(error)) => Some(format!("Resync: {error}")),
+                (None, None) => None,
+            })
+            .flatten()
+            .map(|status| {
+                row![
+                    text(status).width(Length::Fill),
+                    button(text_size_ccff_container("✕", 16))
+                        .on_press(Message::ResyncMarkCancelled)
+                        .width(Length::Fixed(35.0)),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+            });
+
+        let track_picker = self
+            .video
+            .as_ref()
+            .filter(|_| !self.transparent)
+            .map(|video| {
+                let selected = self.selected_track.and_then(|number| video.track(number));
+                row![
+                    text("Subtitle track").width(200),
+                    pick_list(video.tracks.as_slice(), selected, |track: Track| {
+                        Message::TrackSelected(track.number)
+                    })
+                    .width(350)
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+            });
+
+        let episode_picker = self
+            .playlist
+            .as_ref()
+            .filter(|_| !self.transparent)
+            .map(|playlist| {
+                let current = playlist.current;
+                row![
+                    text("Episode").width(200),
+                    button(text("Previous"))
+                        .on_press_maybe(current.checked_sub(1).map(Message::EpisodeSelected)),
+                    pick_list(
+                        playlist.episodes.as_slice(),
+                        Some(playlist.current()),
+                        move |episode: Episode| {
+                            let index =
+                                playlist.episodes.iter().position(|other| *other == episode);
+                            Message::EpisodeSelected(index.unwrap_or(current))
+                        }
+                    )
+                    .width(350),
+                    button(text("Next")).on_press_maybe(
+                        (current + 1 < playlist.episodes.len())
+                            .then_some(Message::EpisodeSelected(current + 1))
+                    ),
+                    text(format!("{} of {}", current + 1, playlist.episodes.len())),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center)
+            });
+
+        let entry_switcher = (self.archive_entries.len() > 1 && !self.transparent).then(|| {
+            row![
+                text("Archive entry").width(200),
+                pick_list(
+                    self.archive_entries.as_slice(),
+                    self.selected_entry.as_ref(),
+                    Message::EntrySelected
+                )
+                .width(Length::Fill)
             ]
             .spacing(10)
-            .padding(15),
+            .align_y(Alignment::Center)
+        });
+
+        let entry_picker = self.entry_picker.as_ref().map(|(path, entries)| {
+            let archive_name = path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            container(
+                column![
+                    row![
+                        text(format!("Pick a subtitle file from {archive_name}"))
+                            .width(Length::Fill),
+                        button(text("Cancel")).on_press(Message::EntryPickerCancelled),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center),
+                    scrollable(column(entries.iter().map(|name| {
+                        button(text(name.as_str()).size(14))
+                            .on_press(Message::EntryPicked(name.clone()))
+                            .style(button::text)
+                            .width(Length::Fill)
+                            .into()
+                    })))
+                    .height(Length::Shrink)
+                    .width(Length::Fill)
+                ]
+                .spacing(10),
+            )
+            .padding(10)
+            .max_height(300)
+            .width(Length::Fill)
+            .style(container::bordered_box)
+        });
+
+        let error = match (&self.load_error, &self.watch_error) {
+            (Some((file_name, error)), _) => Some(format!("Could not load {file_name}: {error}")),
+            (None, watch_error) => watch_error.clone(),
+        };
+        let error_banner = error.map(|error| {
+            container(
+                row![
+                    text(error).width(Length::Fill),
+                    button(text_size_ccff_container("✕", 16))
+                        .on_press(Message::DismissLoadError)
+                        .width(Length::Fixed(35.0)),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+            .padding(10)
+            .width(Length::Fill)
+            .style(|theme: &Theme| {
+                container::Style::default()
+                    .background(theme.palette().danger)
+                    .color(Color::WHITE)
+            })
+        });
+
+        let repair_banner = (!self.repairs.is_empty()).then(|| {
+            let skipped = self.repairs.iter().filter(|repair| repair.skipped).count();
+            let summary = format!(
+                "The file is broken, {} entries were repaired and {skipped} skipped",
+                self.repairs.len() - skipped
+            );
+            let details = self.show_repair_details.then(|| {
+                scrollable(column(
+                    self.repairs
+                        .iter()
+                        .map(|repair| text(repair.to_string()).size(14).into()),
+                ))
+                .height(Length::Shrink)
+                .width(Length::Fill)
+            });
+
+            container(
+                column![
+                    row![
+                        text(summary).width(Length::Fill),
+                        button(text(if self.show_repair_details {
+                            "Hide"
+                        } else {
+                            "Details"
+                        }))
+                        .on_press(Message::ToggleRepairDetails),
+                        button(text_size_ccff_container("✕", 16))
+                            .on_press(Message::DismissRepairs)
+                            .width(Length::Fixed(35.0)),
+                    ]
+                    .spacing(10)
+                    .align_y(Alignment::Center)
+                ]
+                .push_maybe(details)
+                .spacing(10),
+            )
+            .padding(10)
+            .max_height(200)
+            .width(Length::Fill)
+            .style(container::bordered_box)
+        });
+
+        let full_output: Element<'_, Message> = container(
+            column![content_up]
+                .push_maybe(resync_status)
+                .push_maybe(episode_picker)
+                .push_maybe(track_picker)
+                .push_maybe(entry_switcher)
+                .push_maybe(entry_picker)
+                .push_maybe(error_banner)
+                .push_maybe(repair_banner)
+                .push(
+                    container(output)
+                        .align_x(Alignment::Center)
+                        .align_y(Alignment::Center)
+                        .height(Length::Fill)
+                        .width(Length::Fill),
+                )
+                .spacing(10)
+                .padding(15),
         )
         .into();
         //full_output.explain(Color::from_rgb(1.0, 0.0, 0.0))
         full_output
     }
 
+    /// Opens what was picked, dropped or given on the command line. A folder
+    /// or several files become a playlist of episodes.
+    fn open_files(&mut self, mut paths: Vec<PathBuf>) {
+        if let [path] = paths.as_slice()
+            && path.is_dir()
+        {
+            match loader::open_folder(path) {
+                Ok(files) => paths = files,
+                Err(error) => return self.show_load_error(&path.clone(), error),
+            }
+        }
+        match paths.len() {
+            0 => {}
+            1 => {
+                self.playlist = None;
+                self.load_file(paths.remove(0));
+            }
+            _ => self.open_playlist(paths),
+        }
+    }
+
+    fn open_playlist(&mut self, paths: Vec<PathBuf>) {
+        let options = self.load_options();
+        let episodes = paths
+            .into_iter()
+            .map(|path| {
+                // Plain subtitle files are measured now so that any episode
+                // can be jumped to, videos and archives once they are loaded
+                let length_ms = (!loader::is_video(&path) && !loader::is_archive(&path))
+                    .then(|| std::fs::read(&path).ok())
+                    .flatten()
+                    .and_then(|data| loader::load(&path, &data, options).ok())
+                    .map_or(0, |loaded| {
+                        (playlist::length_ms(&loaded.subtitles) as f64 * self.fps_factor()).round()
+                            as u128
+                    });
+                Episode {
+                    path,
+                    length_ms,
+                    offset_ms: None,
+                }
+            })
+            .collect();
+
+        let mut playlist = Playlist::new(episodes);
+        // The offset set so far is where the first episode starts
+        playlist.episodes[0].offset_ms = Some(self.offset_time);
+        self.playlist = Some(playlist);
+        self.play_episode(0, self.offset_time);
+    }
+
+    /// Loads an episode of the playlist. `time` is the playback time with
+    /// the offset, it carries on into the episode.
+    fn play_episode(&mut self, index: usize, time: i128) {
+        let Some(playlist) = &mut self.playlist else {
+            return;
+        };
+        playlist.current = index;
+        let offset_time = playlist.offset_ms(index);
+        let path = playlist.current().path.clone();
+
+        self.load_file(path.clone());
+        if self.loaded_file.as_ref() != Some(&path) {
+            // Subtitles of the episode before are wrong here, and fixing the
+            // file on disk reloads it
+            self.active_subtitles.clear();
+            self.loaded_file = Some(path);
+        }
+        self.offset_time = offset_time;
+        self.offset_str = Timing::from_ms(offset_time).to_string_formatted();
+        self.clock.seek(time - offset_time, Instant::now());
+        self.show_position();
+    }
+
+    /// Changes the playback speed, in steps of [`RATE_STEP`] between
+    /// [`MIN_RATE`] and [`MAX_RATE`].
+    fn set_rate(&mut self, rate: f64) {
+        let rate = ((rate / RATE_STEP).round() * RATE_STEP).clamp(MIN_RATE, MAX_RATE);
+        self.clock.set_rate(rate, Instant::now());
+        self.show_position();
+    }
+
+    /// Reads the clock into what is on screen, and moves on to the next
+    /// episode of a playlist when it is time.
+    fn show_position(&mut self) {
+        self.playback_time = self.clock.position_ms(Instant::now());
+        self.advance_playlist();
+        self.playback_time_str =
+            Timing::from_ms(self.playback_time + self.offset_time).to_string_formatted();
+    }
+
+    /// Moves on to the next episode once the playback reaches its start.
+    fn advance_playlist(&mut self) {
+        let Some(playlist) = &self.playlist else {
+            return;
+        };
+        // An archive of the episode waits for its subtitle file to be picked
+        if self.entry_picker.is_some() {
+            return;
+        }
+        let time = self.playback_time + self.offset_time;
+        if playlist.next_offset_ms().is_some_and(|next| time >= next) {
+            let next = playlist.current + 1;
+            self.play_episode(next, time);
+        }
+    }
+
+    /// Reads and parses a subtitle file, replacing the active subtitles.
+    /// When that fails the old subtitles stay and the error is shown.
+    fn load_file(&mut self, path: PathBuf) {
+        let options = self.load_options();
+        if self.loaded_file.as_ref() != Some(&path) {
+            self.entry_picker = None;
+        }
+        if loader::is_archive(&path) {
+            match loader::open_archive(&path) {
+                Ok(entries) => {
+                    // Reading the same archive again keeps the picked entry
+                    let kept = self.selected_entry.clone().filter(|name| {
+                        self.loaded_file.as_ref() == Some(&path) && entries.contains(name)
+                    });
+                    match kept.or_else(|| (entries.len() == 1).then(|| entries[0].clone())) {
+                        Some(name) => self.load_entry(path, entries, name),
+                        None => self.entry_picker = Some((path, entries)),
+                    }
+                }
+                Err(error) => self.show_load_error(&path, error),
+            }
+            return;
+        }
+        if loader::is_video(&path) {
+            let loaded = loader::open_video(&path).and_then(|video| {
+                // Reading the same video again keeps the picked track
+                let kept = self.selected_track.filter(|&number| {
+                    self.loaded_file.as_ref() == Some(&path) && video.track(number).is_some()
+                });
+                let number = kept
+                    .or_else(|| video.first_track())
+                    .unwrap_or(video.tracks[0].number);
+                let loaded = loader::load_track(&video, number, options)?;
+                Ok((video, number, loaded))
+            });
+            match loaded {
+                Ok((video, number, loaded)) => {
+                    self.show_loaded(path, loaded);
+                    self.video = Some(video);
+                    self.selected_track = Some(number);
+                    self.archive_entries.clear();
+                    self.selected_entry = None;
+                }
+                Err(error) => self.show_load_error(&path, error),
+            }
+            return;
+        }
+
+        let loaded = std::fs::read(&path)
+            .map_err(|error| LoadError::read(&path, error))
+            .and_then(|data| loader::load(&path, &data, options));
+        match loaded {
+            Ok(loaded) => {
+                self.show_loaded(path, loaded);
+                self.video = None;
+                self.selected_track = None;
+                self.archive_entries.clear();
+                self.selected_entry = None;
+            }
+            Err(error) => self.show_load_error(&path, error),
+        }
+    }
+
+    /// Parses one subtitle file of a ZIP archive, `entries` are the other
+    /// files that can be switched to.
+    fn load_entry(&mut self, path: PathBuf, entries: Vec<String>, name: String) {
+        match loader::load_entry(&path, &name, self.load_options()) {
+            Ok(loaded) => {
+                self.show_loaded(path, loaded);
+                self.video = None;
+                self.selected_track = None;
+                self.archive_entries = entries;
+                self.selected_entry = Some(name);
+            }
+            Err(error) => self.show_load_error(Path::new(&name), error),
+        }
+    }
+
+    /// Switches to another subtitle track of the loaded video.
+    fn load_track(&mut self, number: u64) {
+        let (Some(video), Some(path)) = (&self.video, self.loaded_file.clone()) else {
+            return;
+        };
+        match loader::load_track(video, number, self.load_options()) {
+            Ok(loaded) => {
+                self.show_loaded(path, loaded);
+                self.selected_track = Some(number);
+            }
+            Err(error) => self.show_load_error(&path, error),
+        }
+    }
+
+    fn show_loaded(
+        &mut self,
+        path: PathBuf,
+        Loaded {
+            mut subtitles,
+            repairs,
+            encoding,
+        }: Loaded,
+    ) {
+        if self.loaded_file.as_ref() != Some(&path) {
+            self.last_reload = None;
+            self.watch_error = None;
+            // A resync only fits the file it was made for
+            self.resync = None;
+            self.resync_undo.clear();
+        }
+        // The marked line may start somewhere else now
+        self.resync_mark = None;
+        self.source_length_ms = playlist::length_ms(&subtitles);
+        self.convert_frame_rate(&mut subtitles);
+        if let Some(Resync { scale, shift_ms }) = self.resync {
+            for subtitle in subtitles.iter_mut() {
+                subtitle.retime(scale, shift_ms);
+            }
+        }
+        self.apply_settings(&mut subtitles, &self.active_sub_font);
+        if let Some(playlist) = &mut self.playlist
+            && playlist.current().path == path
+        {
+            playlist.current_mut().length_ms = playlist::length_ms(&subtitles);
+        }
+        self.active_subtitles = subtitles;
+        self.repairs = repairs;
+        self.show_repair_details = false;
+        self.detected_encoding = encoding;
+        self.loaded_file = Some(path);
+        self.load_error = None;
+    }
+
+    fn show_load_error(&mut self, path: &Path, error: LoadError) {
+        let file_name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        self.load_error = Some((file_name, error));
+    }
+
+    /// Reads the second subtitle file, the first readable track for a video
+    /// and the first subtitle file of an archive.
+    fn load_secondary_file(&mut self, path: PathBuf) {
+        let options = self.load_options();
+        let loaded = if loader::is_archive(&path) {
+            loader::open_archive(&path)
+                .and_then(|entries| loader::load_entry(&path, &entries[0], options))
+        } else if loader::is_video(&path) {
+            loader::open_video(&path).and_then(|video| {
+                let number = video.first_track().unwrap_or(video.tracks[0].number);
+                loader::load_track(&video, number, options)
+            })
+        } else {
+            std::fs::read(&path)
+                .map_err(|error| LoadError::read(&path, error))
+                .and_then(|data| loader::load(&path, &data, options))
+        };
+
+        match loaded {
+            Ok(Loaded { mut subtitles, .. }) => {
+                self.convert_frame_rate(&mut subtitles);
+                self.apply_settings(&mut subtitles, &self.secondary.font);
+                for subtitle in subtitles.iter_mut() {
+                    subtitle.color = self.secondary.color.color();
+                }
+                self.secondary.subtitles = subtitles;
+                self.secondary.file = Some(path);
+            }
+            Err(error) => self.show_load_error(&path, error),
+        }
+    }
+
+    /// Parses the loaded file again, for settings that change how it is parsed.
+    fn reload_file(&mut self) {
+        if let Some(path) = self.loaded_file.clone() {
+            self.load_file(path);
+        }
+    }
+
+    fn reload_secondary_file(&mut self) {
+        if let Some(path) = self.secondary.file.clone() {
+            self.load_secondary_file(path);
+        }
+    }
+
+    /// Stretches subtitles timed for another frame rate than the video's,
+    /// both the main and the second ones are made for the same video.
+    fn convert_frame_rate(&self, subtitles: &mut [Subtitle]) {
+        let factor = self.fps_factor();
+        if factor != 1.0 {
+            for subtitle in subtitles.iter_mut() {
+                subtitle.retime(factor, 0);
+            }
+        }
+    }
+
+    fn load_options(&self) -> LoadOptions {
+        LoadOptions {
+            fps: self.fps(),
+            encoding: self.encoding,
+        }
+    }
+
+    fn fps(&self) -> f64 {
+        self.frame_rate.fps().unwrap_or(self.custom_frame_rate)
+    }
+
+    /// How much longer the subtitles get after the frame rate conversion.
+    fn fps_factor(&self) -> f64 {
+        self.convert_from
+            .fps()
+            .zip(self.convert_to.fps())
+            .map_or(1.0, |(from, to)| from / to)
+    }
+
+    /// Marks the subtitle starting nearest to now as one that should start
+    /// now. The second mark stretches and moves all subtitles so that both
+    /// marked ones start when they should.
+    fn mark_resync(&mut self) {
+        let now = self.clock.position_ms(Instant::now());
+        let Some(nearest) = self
+            .active_subtitles
+            .iter()
+            .min_by_key(|subtitle| (subtitle.start_time_ms - now).abs())
+        else {
+            return;
+        };
+        let starts = nearest.start_time_ms;
+        let line = nearest.plain_text().replace('\n', " ");
+        self.resync_error = None;
+
+        let Some((first_starts, first_should_start, _)) = self.resync_mark.take() else {
+            self.resync_mark = Some((starts, now, line));
+            return;
+        };
+        match Resync::from_points((first_starts, starts), (first_should_start, now)) {
+            Some(resync) => {
+                self.resync_undo.push(self.resync);
+                self.resync = Some(self.resync.map_or(resync, |before| before.then(resync)));
+                self.reload_file();
+            }
+            None => {
+                self.resync_error = Some(String::from(
+                    "the two marks need two different lines, in the order they show",
+                ))
+            }
+        }
+    }
+
+    /// Applies the font and fade settings to freshly loaded subtitles.
+    fn apply_settings(&self, subtitles: &mut [Subtitle], sub_font: &str) {
+        let fade = self.default_fade();
+        for subtitle in subtitles {
+            // A font picked in the settings wins over the file's fonts
+            let font_name = match &subtitle.style {
+                Some(style) if sub_font.is_empty() => &style.font_name,
+                _ => sub_font,
+            };
+            subtitle.font = Font {
+                family: iced::font::Family::Name(subtitle::font_family(font_name)),
+                ..Default::default()
+            };
+            if subtitle.style.is_none() {
+                subtitle.fade = fade;
+            }
+        }
+    }
+
+    fn default_fade(&self) -> Option<Fade> {
+        (self.default_fade_ms > 0).then_some(Fade::Simple {
+            fade_in_ms: self.default_fade_ms,
+            fade_out_ms: self.default_fade_ms,
+        })
+    }
+
     fn subscription(&self) -> Subscription<Message> {
         let mut subs = vec![];
         use keyboard::Key::Named;
@@ -478,10 +1840,28 @@ impl IcedSubtitleWatcher {
         subs.push(keyboard::on_key_press(|key, _modifiers| match key {
             Named(KeyName::Escape) => Some(Message::ToggleTransparency),
             Named(KeyName::Space) => Some(Message::KeySpacePressed),
+            Named(KeyName::Backspace) => Some(Message::RateReset),
+            keyboard::Key::Character(c) if c == "m" => Some(Message::ResyncMarkPressed),
+            keyboard::Key::Character(c) if c == "[" => Some(Message::SlowerPressed),
+            keyboard::Key::Character(c) if c == "]" => Some(Message::FasterPressed),
             _ => None,
         }));
 
-        subs.push(if self.play {
+        subs.push(event::listen_with(|event, _status, _window| match event {
+            Event::Window(iced::window::Event::FileDropped(path)) => {
+                Some(Message::FileDropped(path))
+            }
+            _ => None,
+        }));
+
+        if let Some(path) = &self.loaded_file {
+            subs.push(watcher::watch(path.clone()).map(|change| match change {
+                watcher::Change::Modified(path) => Message::FileChanged(path),
+                watcher::Change::Failed(error) => Message::WatchFailed(error),
+            }));
+        }
+
+        subs.push(if self.clock.is_playing() {
             iced::time::every(std::time::Duration::from_millis(10)).map(|_| Message::Tick)
         } else {
             Subscription::none()
@@ -491,34 +1871,9 @@ impl IcedSubtitleWatcher {
     }
 }
 
-struct Subtitle {
-    start_time_ms: u128,
-    end_time_ms: u128,
-    text: String,
-    font: Font,
-}
-
-impl Subtitle {
-    fn _new<T: Into<String>>(start_t: u128, end_t: u128, text: T, font: Font) -> Self {
-        Subtitle {
-            start_time_ms: start_t,
-            end_time_ms: end_t,
-            text: text.into().clone(),
-            font: font,
-        }
-    }
-
-    fn view<'a>(
-        &self,
-        font_size: u16,
-    ) -> iced::widget::text::Rich<'a, Message, Theme, iced::Renderer> {
-        rich_text![span(self.text.clone())]
-            .size(font_size)
-            .font(self.font)
-    }
-}
-
+/// A time as `hh:mm:ss:mmm`, before the start when `negative`.
 struct Timing {
+    negative: bool,
     hh: u128,
     mm: u128,
     ss: u128,
@@ -526,29 +1881,18 @@ struct Timing {
 }
 
 impl Timing {
-    fn _from_string_ms(input: String) -> Option<Timing> {
-        if let Ok(valid_number) = input.parse::<u128>() {
-            let secs = valid_number / 1000;
-            let time_ms = valid_number % 1000;
-            let time_ss = secs % 60;
-            let time_mm = (secs / 60) % 60;
-            let time_hh = secs / (60 * 60);
-            return Some(Timing {
-                hh: time_hh,
-                mm: time_mm,
-                ss: time_ss,
-                ms: time_ms,
-            });
-        } else {
-            return None;
-        }
+    fn from_string_ms(input: String) -> Option<Timing> {
+        input.parse::<i128>().ok().map(Timing::from_ms)
     }
+    /// A leading `-` makes it a time before the start.
     fn from_string_fmtd(input: String) -> Option<Timing> {
+        let (negative, input) = match input.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, input.as_str()),
+        };
         let iter_timing = input
             .split(":")
-            .map(|item| item.to_string().parse::<u128>())
-            .filter(|item| item.is_ok())
-            .map(|item| item.unwrap())
+            .filter_map(|item| item.parse::<u128>().ok())
             .collect::<Vec<u128>>();
 
         if iter_timing.len() != 4 {
@@ -560,28 +1904,28 @@ impl Timing {
         let ss = iter_timing[2];
         let ms = iter_timing[3];
 
-        if ms >= 1000 {
-            return None;
-        } else if ss >= 60 {
-            return None;
-        } else if mm >= 60 {
+        if ms >= 1000 || ss >= 60 || mm >= 60 {
             return None;
         }
 
         Some(Timing {
-            hh: hh,
-            mm: mm,
-            ss: ss,
-            ms: ms,
+            negative,
+            hh,
+            mm,
+            ss,
+            ms,
         })
     }
-    fn from_u128_ms(input: u128) -> Timing {
+    fn from_ms(input: i128) -> Timing {
+        let negative = input < 0;
+        let input = input.unsigned_abs();
         let secs = input / 1000;
         let time_ms = input % 1000;
         let time_ss = secs % 60;
         let time_mm = (secs / 60) % 60;
         let time_hh = secs / (60 * 60);
         Timing {
+            negative,
             hh: time_hh,
             mm: time_mm,
             ss: time_ss,
@@ -590,35 +1934,36 @@ impl Timing {
     }
     fn to_string_formatted(&self) -> String {
         format!(
-            "{:02}:{:02}:{:02}:{:03}",
-            self.hh, self.mm, self.ss, self.ms
+            "{}{:02}:{:02}:{:02}:{:03}",
+            if self.negative { "-" } else { "" },
+            self.hh,
+            self.mm,
+            self.ss,
+            self.ms
         )
     }
-    fn to_u128_ms(&self) -> u128 {
-        self.hh * 3600000 + self.mm * 60000 + self.ss * 1000 + self.ms
+    fn to_ms(&self) -> i128 {
+        let ms = (self.hh * 3600000 + self.mm * 60000 + self.ss * 1000 + self.ms) as i128;
+        if self.negative { -ms } else { ms }
     }
 }
 
-fn strip_tags(input: &str, delim_start: char, delim_end: char) -> String {
-    let mut output = String::new();
-    let mut count: i64 = 0;
-    for c in input.chars() {
-        if c == delim_start {
-            count += 1;
-            continue;
-        } else if c == delim_end {
-            count -= 1;
-            continue;
-        }
-        if count == 0 {
-            if c == '\n' {
-                output.push(' ');
-            } else {
-                output.push(c);
-            }
-        }
-    }
-    output
+/// The subtitles shown at `time`, laid out in whatever space they get.
+fn subtitle_area(
+    subtitles: &[Subtitle],
+    time: i128,
+    font_size: u16,
+    unplaced_y: Vertical,
+) -> Element<'_, Message> {
+    let subs_to_diplay = subtitles
+        .iter()
+        .filter(|subtitle| (time >= subtitle.start_time_ms) && (time <= subtitle.end_time_ms))
+        .collect::<Vec<&Subtitle>>();
+
+    responsive(move |size| {
+        subtitle::view_placed(&subs_to_diplay, size, font_size, time, unplaced_y)
+    })
+    .into()
 }
 
 fn better_button<'a, T: Into<String> + iced::widget::text::IntoFragment<'a>>(
@@ -633,7 +1978,6 @@ fn better_button<'a, T: Into<String> + iced::widget::text::IntoFragment<'a>>(
             true => None,
             false => Some(message),
         })
-        .into()
 }
 
 fn text_size_ccff_container<'a, T: Into<String> + iced::widget::text::IntoFragment<'a>>(
@@ -645,5 +1989,92 @@ fn text_size_ccff_container<'a, T: Into<String> + iced::widget::text::IntoFragme
         .align_y(Alignment::Center)
         .width(Length::Fill)
         .height(Length::Shrink)
-        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher(offset_ms: i128) -> IcedSubtitleWatcher {
+        let args = Args {
+            offset_ms: Some(offset_ms),
+            ..Args::default()
+        };
+        IcedSubtitleWatcher::new(args, UtcOffset::UTC).0
+    }
+
+    #[test]
+    fn typed_time_includes_a_negative_offset() {
+        let mut watcher = watcher(-2500);
+        let _ = watcher.update(Message::PlaybackTimeEdited(String::from("00:01:00:000")));
+        assert_eq!(watcher.playback_time, 62_500);
+
+        // The field keeps showing what was typed
+        let _ = watcher.update(Message::Tick);
+        assert_eq!(watcher.playback_time_str, "00:01:00:000");
+    }
+
+    /// Where `resync` moves a subtitle starting at `ms`.
+    fn resynced(resync: Resync, ms: i128) -> i128 {
+        let mut subtitle = Subtitle::new(ms, ms);
+        subtitle.retime(resync.scale, resync.shift_ms);
+        subtitle.start_time_ms
+    }
+
+    #[test]
+    fn marked_subtitles_land_on_their_marks() {
+        let resync = Resync::from_points((10_000, 70_000), (12_000, 69_000)).unwrap();
+        assert_eq!(resynced(resync, 10_000), 12_000);
+        assert_eq!(resynced(resync, 70_000), 69_000);
+        // Two lines that would swap or fall together
+        assert!(Resync::from_points((10_000, 70_000), (69_000, 12_000)).is_none());
+        assert!(Resync::from_points((10_000, 10_000), (12_000, 69_000)).is_none());
+    }
+
+    #[test]
+    fn resyncs_one_after_the_other() {
+        let first = Resync {
+            scale: 1.0,
+            shift_ms: -3000,
+        };
+        let second = Resync {
+            scale: 1.5,
+            shift_ms: -500,
+        };
+        let both = first.then(second);
+        for ms in [-4000, 0, 1000, 10_000, 3_600_000] {
+            assert_eq!(resynced(both, ms), resynced(second, resynced(first, ms)));
+        }
+        assert_eq!(resynced(both, 1000), -3500);
+    }
+
+    #[test]
+    fn marking_two_lines_resyncs_the_file() {
+        let path = std::env::temp_dir().join(format!("{}-resync.srt", std::process::id()));
+        std::fs::write(
+            &path,
+            "1\n00:00:10,000 --> 00:00:11,000\nOne\n\n\
+             2\n00:00:40,000 --> 00:00:41,000\nTwo\n\n\
+             3\n00:01:10,000 --> 00:01:11,000\nThree\n",
+        )
+        .unwrap();
+        let mut watcher = watcher(0);
+        let _ = watcher.update(Message::FileDropped(path.clone()));
+
+        // Each mark picks the line starting closest to the playback time
+        let _ = watcher.update(Message::PlaybackTimeEdited(String::from("00:00:12:000")));
+        let _ = watcher.update(Message::ResyncMarkPressed);
+        let _ = watcher.update(Message::PlaybackTimeEdited(String::from("00:01:09:000")));
+        let _ = watcher.update(Message::ResyncMarkPressed);
+        std::fs::remove_file(&path).unwrap();
+
+        let starts = watcher
+            .active_subtitles
+            .iter()
+            .map(|subtitle| subtitle.start_time_ms)
+            .collect::<Vec<i128>>();
+        assert_eq!(starts, [12_000, 40_500, 69_000]);
+        assert!(watcher.resync_mark.is_none());
+        assert_eq!(watcher.resync_undo.len(), 1);
+    }
 }
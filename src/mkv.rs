@@ -0,0 +1,598 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use flate2::read::ZlibDecoder;
+
+use crate::video::{Block, Codec, Track, Video};
+
+/// How long a subtitle stays when its block has no duration and is the last one.
+const LAST_SUBTITLE_MS: i128 = 5000;
+
+const EBML: u32 = 0x1a45dfa3;
+const SEGMENT: u32 = 0x18538067;
+const INFO: u32 = 0x1549a966;
+const TIMESTAMP_SCALE: u32 = 0x2ad7b1;
+const TRACKS: u32 = 0x1654ae6b;
+const TRACK_ENTRY: u32 = 0xae;
+const TRACK_NUMBER: u32 = 0xd7;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63a2;
+const LANGUAGE: u32 = 0x22b59c;
+const LANGUAGE_BCP47: u32 = 0x22b59d;
+const NAME: u32 = 0x536e;
+const FLAG_DEFAULT: u32 = 0x88;
+const FLAG_FORCED: u32 = 0x55aa;
+const CONTENT_ENCODINGS: u32 = 0x6d80;
+const CONTENT_ENCODING: u32 = 0x6240;
+const CONTENT_ENCODING_SCOPE: u32 = 0x5032;
+const CONTENT_ENCODING_TYPE: u32 = 0x5033;
+const CONTENT_COMPRESSION: u32 = 0x5034;
+const CONTENT_COMP_ALGO: u32 = 0x4254;
+const CONTENT_COMP_SETTINGS: u32 = 0x4255;
+const CLUSTER: u32 = 0x1f43b675;
+const TIMESTAMP: u32 = 0xe7;
+const SIMPLE_BLOCK: u32 = 0xa3;
+const BLOCK_GROUP: u32 = 0xa0;
+const BLOCK: u32 = 0xa1;
+const BLOCK_DURATION: u32 = 0x9b;
+const BLOCK_ADDITIONS: u32 = 0x75a1;
+const BLOCK_MORE: u32 = 0xa6;
+const BLOCK_ADDITIONAL: u32 = 0xa5;
+
+const SUBTITLE_TRACK: u64 = 0x11;
+
+#[derive(Debug, Clone)]
+enum Compression {
+    Zlib,
+    /// The bytes were cut off the start of every frame.
+    HeaderStripping(Vec<u8>),
+}
+
+impl Compression {
+    fn decompress(&self, data: Vec<u8>) -> Vec<u8> {
+        match self {
+            Compression::Zlib => {
+                let mut decompressed = Vec::new();
+                match ZlibDecoder::new(data.as_slice()).read_to_end(&mut decompressed) {
+                    Ok(_) => decompressed,
+                    Err(_) => data,
+                }
+            }
+            Compression::HeaderStripping(header) => [header.as_slice(), &data].concat(),
+        }
+    }
+}
+
+/// A block before the timestamp scale is known.
+struct RawBlock {
+    /// In timestamp ticks.
+    time: i64,
+    duration: Option<u64>,
+    data: Vec<u8>,
+    additional: Option<Vec<u8>>,
+}
+
+/// Reads the subtitle tracks and their blocks from a Matroska file. Only
+/// the headers of video and audio blocks are read, the rest is skipped.
+/// A file that ends early, like one still downloading, keeps what was read.
+pub fn read(path: &Path) -> io::Result<Video> {
+    let file = File::open(path)?;
+    let length = file.metadata()?.len();
+    let mut reader = EbmlReader {
+        inner: BufReader::new(file),
+        position: 0,
+        length,
+    };
+
+    match reader.header()? {
+        Some((EBML, Some(size))) => reader.skip(size)?,
+        _ => return Err(invalid_data("not a Matroska file")),
+    }
+
+    let mut timestamp_scale = 1_000_000;
+    let mut tracks = Vec::new();
+    let mut raw_blocks: HashMap<u64, Vec<RawBlock>> = HashMap::new();
+    let mut cluster_time = 0;
+
+    let mut read_elements = || -> io::Result<()> {
+        while reader.position < length {
+            let Some((id, size)) = reader.header()? else {
+                break;
+            };
+            // Segments and clusters can have an unknown size, their
+            // children are read as if they came one after the other
+            if id == SEGMENT || id == CLUSTER {
+                continue;
+            }
+            let size = size.ok_or_else(|| invalid_data("element of unknown size"))?;
+
+            match id {
+                INFO => {
+                    let info = reader.read(size)?;
+                    if let Some(scale) = Elements(&info).child(TIMESTAMP_SCALE) {
+                        timestamp_scale = read_uint(scale);
+                    }
+                }
+                TRACKS => {
+                    let data = reader.read(size)?;
+                    tracks = Elements(&data)
+                        .filter(|(id, _)| *id == TRACK_ENTRY)
+                        .filter_map(|(_, entry)| parse_track(entry))
+                        .collect();
+                    for (track, _) in &tracks {
+                        raw_blocks.entry(track.number).or_default();
+                    }
+                }
+                TIMESTAMP => cluster_time = read_uint(&reader.read(size)?),
+                SIMPLE_BLOCK => {
+                    let end = reader.end(size)?;
+                    let (track, time) = reader.block_header()?;
+                    let rest = reader.left_until(end)?;
+                    if let Some(blocks) = raw_blocks.get_mut(&track) {
+                        blocks.push(RawBlock {
+                            time: cluster_time as i64 + i64::from(time),
+                            duration: None,
+                            data: reader.read(rest)?,
+                            additional: None,
+                        });
+                    } else {
+                        reader.skip(rest)?;
+                    }
+                }
+                BLOCK_GROUP => {
+                    if let Some((track, block)) = reader.block_group(size, &raw_blocks)? {
+                        raw_blocks.entry(track).or_default().push(RawBlock {
+                            time: cluster_time as i64 + block.time,
+                            ..block
+                        });
+                    }
+                }
+                _ => reader.skip(size)?,
+            }
+        }
+        Ok(())
+    };
+    match read_elements() {
+        Err(error) if error.kind() != io::ErrorKind::UnexpectedEof => return Err(error),
+        _ => {}
+    }
+
+    let to_ms = |ticks: i64| i128::from(ticks) * i128::from(timestamp_scale) / 1_000_000;
+    let blocks = tracks
+        .iter()
+        .map(|(track, compression)| {
+            let raw = raw_blocks.remove(&track.number).unwrap_or_default();
+            let starts = raw
+                .iter()
+                .map(|block| to_ms(block.time))
+                .collect::<Vec<i128>>();
+            let blocks = raw
+                .into_iter()
+                .enumerate()
+                .map(|(index, block)| {
+                    let start_time_ms = starts[index];
+                    // Without a duration a subtitle lasts until the next one
+                    let end_time_ms = match block.duration {
+                        Some(duration) => to_ms(block.time + duration as i64),
+                        None => starts
+                            .get(index + 1)
+                            .copied()
+                            .unwrap_or(start_time_ms + LAST_SUBTITLE_MS),
+                    };
+                    let data = match compression {
+                        Some(compression) => compression.decompress(block.data),
+                        None => block.data,
+                    };
+                    Block {
+                        start_time_ms,
+                        end_time_ms,
+                        data,
+                        additional: block.additional,
+                    }
+                })
+                .collect();
+            (track.number, blocks)
+        })
+        .collect();
+
+    Ok(Video {
+        tracks: tracks.into_iter().map(|(track, _)| track).collect(),
+        blocks,
+    })
+}
+
+/// A subtitle track entry, with how its blocks are compressed.
+fn parse_track(entry: &[u8]) -> Option<(Track, Option<Compression>)> {
+    let elements = Elements(entry);
+    if elements.child(TRACK_TYPE).map(read_uint) != Some(SUBTITLE_TRACK) {
+        return None;
+    }
+
+    let mut compression = None;
+    let mut codec_private = elements.child(CODEC_PRIVATE).unwrap_or_default().to_vec();
+    if let Some(encodings) = elements.child(CONTENT_ENCODINGS) {
+        for (_, encoding) in Elements(encodings).filter(|(id, _)| *id == CONTENT_ENCODING) {
+            let encoding = Elements(encoding);
+            // Type 1 is encryption, nothing to do about that
+            if encoding.child(CONTENT_ENCODING_TYPE).map_or(0, read_uint) != 0 {
+                continue;
+            }
+            let Some(settings) = encoding.child(CONTENT_COMPRESSION).map(Elements) else {
+                continue;
+            };
+            let found = match settings.child(CONTENT_COMP_ALGO).map_or(0, read_uint) {
+                0 => Compression::Zlib,
+                3 => Compression::HeaderStripping(
+                    settings
+                        .child(CONTENT_COMP_SETTINGS)
+                        .unwrap_or_default()
+                        .to_vec(),
+                ),
+                _ => continue,
+            };
+            // Scope 1 is the frames, 2 the codec private data
+            let scope = encoding.child(CONTENT_ENCODING_SCOPE).map_or(1, read_uint);
+            if scope & 2 != 0 {
+                codec_private = found.decompress(codec_private);
+            }
+            if scope & 1 != 0 {
+                compression = Some(found);
+            }
+        }
+    }
+
+    let string = |id| elements.child(id).map(read_string);
+    let codec = match string(CODEC_ID).unwrap_or_default().as_str() {
+        "S_TEXT/UTF8" | "S_TEXT/ASCII" => Codec::Srt,
+        "S_TEXT/ASS" | "S_ASS" => Codec::Ass,
+        "S_TEXT/SSA" | "S_SSA" => Codec::Ssa,
+        "S_TEXT/WEBVTT" | "D_WEBVTT/SUBTITLES" => Codec::WebVtt,
+        "S_HDMV/PGS" => Codec::Other(String::from("PGS")),
+        "S_VOBSUB" => Codec::Other(String::from("VobSub")),
+        codec_id => Codec::Other(codec_id.to_string()),
+    };
+    let track = Track {
+        number: elements.child(TRACK_NUMBER).map(read_uint)?,
+        codec,
+        codec_private,
+        language: string(LANGUAGE_BCP47)
+            .or_else(|| string(LANGUAGE))
+            .unwrap_or_else(|| String::from("eng")),
+        name: string(NAME).filter(|name| !name.is_empty()),
+        default: elements
+            .child(FLAG_DEFAULT)
+            .is_none_or(|flag| read_uint(flag) != 0),
+        forced: elements
+            .child(FLAG_FORCED)
+            .is_some_and(|flag| read_uint(flag) != 0),
+    };
+    Some((track, compression))
+}
+
+struct EbmlReader {
+    inner: BufReader<File>,
+    position: u64,
+    /// Of the whole file, no element read into memory can be larger.
+    length: u64,
+}
+
+impl EbmlReader {
+    /// The ID and size of the next element, `None` at the end of the file.
+    /// The size is `None` when it is unknown.
+    fn header(&mut self) -> io::Result<Option<(u32, Option<u64>)>> {
+        let mut first = [0u8];
+        if self.inner.read(&mut first)? == 0 {
+            return Ok(None);
+        }
+        self.position += 1;
+        let (id, _) = self.vint(first[0], true)?;
+        let mut first = [0u8];
+        self.read_exact(&mut first)?;
+        let (size, length) = self.vint(first[0], false)?;
+        // All value bits set means the size is unknown
+        let unknown = size == (1 << (7 * length)) - 1;
+        Ok(Some((id as u32, (!unknown).then_some(size))))
+    }
+
+    /// Reads the rest of a variable length integer that starts with `first`,
+    /// with the length marker kept for IDs.
+    fn vint(&mut self, first: u8, keep_marker: bool) -> io::Result<(u64, u32)> {
+        let length = first.leading_zeros() + 1;
+        if length > 8 {
+            return Err(invalid_data("broken variable length integer"));
+        }
+        let mut value = if keep_marker {
+            u64::from(first)
+        } else {
+            u64::from(first) & (0xff >> length)
+        };
+        for _ in 1..length {
+            let mut byte = [0u8];
+            self.read_exact(&mut byte)?;
+            value = value << 8 | u64::from(byte[0]);
+        }
+        Ok((value, length))
+    }
+
+    /// The track number and relative timestamp at the start of a block.
+    fn block_header(&mut self) -> io::Result<(u64, i16)> {
+        let mut first = [0u8];
+        self.read_exact(&mut first)?;
+        let (track, _) = self.vint(first[0], false)?;
+        let mut rest = [0u8; 3];
+        self.read_exact(&mut rest)?;
+        Ok((track, i16::from_be_bytes([rest[0], rest[1]])))
+    }
+
+    /// Reads a block group when its block belongs to one of `tracks`,
+    /// skipping it otherwise.
+    fn block_group(
+        &mut self,
+        size: u64,
+        tracks: &HashMap<u64, Vec<RawBlock>>,
+    ) -> io::Result<Option<(u64, RawBlock)>> {
+        let end = self.end(size)?;
+        let mut found = None;
+        let mut duration = None;
+        let mut additional = None;
+
+        while self.position < end {
+            let Some((id, Some(size))) = self.header()? else {
+                return Err(invalid_data("broken block group"));
+            };
+            match id {
+                BLOCK => {
+                    let block_end = self.end(size)?;
+                    let (track, time) = self.block_header()?;
+                    if !tracks.contains_key(&track) {
+                        self.skip(self.left_until(end)?)?;
+                        return Ok(None);
+                    }
+                    found = Some((track, time, self.read(self.left_until(block_end)?)?));
+                }
+                BLOCK_DURATION => duration = Some(read_uint(&self.read(size)?)),
+                BLOCK_ADDITIONS => {
+                    let data = self.read(size)?;
+                    additional = Elements(&data)
+                        .child(BLOCK_MORE)
+                        .and_then(|more| Elements(more).child(BLOCK_ADDITIONAL))
+                        .map(<[u8]>::to_vec);
+                }
+                _ => self.skip(size)?,
+            }
+        }
+
+        Ok(found.map(|(track, time, data)| {
+            (
+                track,
+                RawBlock {
+                    time: i64::from(time),
+                    duration,
+                    data,
+                    additional,
+                },
+            )
+        }))
+    }
+
+    /// Where an element of `size` that starts here ends.
+    fn end(&self, size: u64) -> io::Result<u64> {
+        self.position
+            .checked_add(size)
+            .ok_or_else(|| invalid_data("too large"))
+    }
+
+    /// How much of an element ending at `end` is left, an error when its
+    /// contents went past it.
+    fn left_until(&self, end: u64) -> io::Result<u64> {
+        end.checked_sub(self.position)
+            .ok_or_else(|| invalid_data("block larger than its element"))
+    }
+
+    fn read(&mut self, size: u64) -> io::Result<Vec<u8>> {
+        // A broken size would otherwise allocate gigabytes. It is the end of
+        // the file that is missing, like when reading one still downloading,
+        // so what was read before is kept.
+        if size > self.length.saturating_sub(self.position) {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "element larger than the file",
+            ));
+        }
+        let mut data = vec![0u8; usize::try_from(size).map_err(|_| invalid_data("too large"))?];
+        self.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    fn read_exact(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buffer)?;
+        self.position += buffer.len() as u64;
+        Ok(())
+    }
+
+    fn skip(&mut self, size: u64) -> io::Result<()> {
+        let offset = i64::try_from(size).map_err(|_| invalid_data("too large"))?;
+        self.inner.seek_relative(offset)?;
+        self.position += size;
+        Ok(())
+    }
+}
+
+/// The child elements of an element read into memory.
+#[derive(Clone, Copy)]
+struct Elements<'a>(&'a [u8]);
+
+impl<'a> Elements<'a> {
+    /// The first child with this ID.
+    fn child(mut self, wanted: u32) -> Option<&'a [u8]> {
+        self.find(|(id, _)| *id == wanted).map(|(_, data)| data)
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = (u32, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, id_length) = slice_vint(self.0, true)?;
+        let (size, size_length) = slice_vint(&self.0[id_length..], false)?;
+        let start = id_length + size_length;
+        let end = start.checked_add(usize::try_from(size).ok()?)?;
+        let data = self.0.get(start..end)?;
+        self.0 = &self.0[end..];
+        Some((id as u32, data))
+    }
+}
+
+fn slice_vint(data: &[u8], keep_marker: bool) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let length = first.leading_zeros() as usize + 1;
+    if length > 8 {
+        return None;
+    }
+    let mut value = if keep_marker {
+        u64::from(first)
+    } else {
+        u64::from(first) & (0xff >> length)
+    };
+    for byte in data.get(1..length)? {
+        value = value << 8 | u64::from(*byte);
+    }
+    Some((value, length))
+}
+
+fn read_uint(data: &[u8]) -> u64 {
+    data.iter()
+        .take(8)
+        .fold(0, |value, byte| value << 8 | u64::from(*byte))
+}
+
+/// Strings can be padded with zero bytes.
+fn read_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::*;
+
+    /// An element with a size short enough for one byte.
+    fn element(id: &[u8], data: &[u8]) -> Vec<u8> {
+        [id, &[0x80 | data.len() as u8], data].concat()
+    }
+
+    fn read_bytes(name: &str, data: &[u8]) -> io::Result<Video> {
+        let path: PathBuf = env::temp_dir().join(format!("{}-{name}.mkv", std::process::id()));
+        fs::write(&path, data)?;
+        let video = read(&path);
+        fs::remove_file(&path)?;
+        video
+    }
+
+    fn header_and_tracks() -> Vec<u8> {
+        let track = [
+            element(&[0xd7], &[1]),
+            element(&[0x83], &[0x11]),
+            element(&[0x86], b"S_TEXT/UTF8"),
+        ]
+        .concat();
+        [
+            element(&[0x1a, 0x45, 0xdf, 0xa3], &[]),
+            // A segment of unknown size
+            vec![
+                0x18, 0x53, 0x80, 0x67, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ],
+            element(&[0x16, 0x54, 0xae, 0x6b], &element(&[0xae], &track)),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn simple_blocks_and_block_groups() {
+        let cluster = [
+            // Cluster timestamp 1000
+            element(&[0xe7], &[0x03, 0xe8]),
+            element(&[0xa3], &[[0x81, 0, 0, 0x80].as_slice(), b"Hello"].concat()),
+            // A block of a track that is not subtitles is skipped
+            element(&[0xa3], &[0x82, 0, 0, 0x80, 1, 2, 3]),
+            element(
+                &[0xa0],
+                &[
+                    // 500 after the cluster, for 2000
+                    element(
+                        &[0xa1],
+                        &[[0x81, 0x01, 0xf4, 0].as_slice(), b"World"].concat(),
+                    ),
+                    element(&[0x9b], &[0x07, 0xd0]),
+                ]
+                .concat(),
+            ),
+        ]
+        .concat();
+        let data = [
+            header_and_tracks(),
+            element(&[0x1f, 0x43, 0xb6, 0x75], &cluster),
+        ]
+        .concat();
+
+        let video = read_bytes("blocks", &data).unwrap();
+        assert_eq!(video.tracks.len(), 1);
+        assert_eq!(video.tracks[0].codec, Codec::Srt);
+        let blocks = video
+            .blocks(1)
+            .iter()
+            .map(|block| {
+                (
+                    block.start_time_ms,
+                    block.end_time_ms,
+                    block.data.as_slice(),
+                )
+            })
+            .collect::<Vec<(i128, i128, &[u8])>>();
+        assert_eq!(
+            blocks,
+            [
+                (1000, 1500, b"Hello".as_slice()),
+                (1500, 3500, b"World".as_slice())
+            ]
+        );
+    }
+
+    #[test]
+    fn element_larger_than_the_file() {
+        let data = [
+            header_and_tracks(),
+            // Info of 4 GiB, in a file of a few bytes
+            vec![0x15, 0x49, 0xa9, 0x66, 0x01, 0, 0, 1, 0, 0, 0, 0],
+            vec![0; 16],
+        ]
+        .concat();
+        let video = read_bytes("huge", &data).unwrap();
+        assert_eq!(video.tracks.len(), 1);
+    }
+
+    #[test]
+    fn block_larger_than_its_element() {
+        let data = [
+            header_and_tracks(),
+            // The block header alone is longer than the 2 bytes of the block
+            element(&[0xa3], &[0x81, 0]),
+            vec![0; 16],
+        ]
+        .concat();
+        let error = read_bytes("overrun", &data).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
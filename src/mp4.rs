@@ -0,0 +1,700 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::video::{Block, Codec, Track, Video};
+
+/// Samples bigger than this are not text, the file is broken.
+const MAX_SAMPLE_SIZE: u32 = 1 << 24;
+
+/// Where a sample is in the file and when it is shown, in the track's timescale.
+struct Sample {
+    offset: u64,
+    size: u32,
+    time: u64,
+    duration: u64,
+}
+
+/// What is needed from a `trak` box to read the samples of a text track.
+struct TextTrack {
+    track: Track,
+    timescale: u64,
+    /// Added to the sample times, from the edit list.
+    shift: i64,
+    samples: Vec<Sample>,
+}
+
+/// Reads the text tracks of an MP4 (ISO base media) file, with the sample
+/// tables of a plain file or the fragments of a fragmented one. Only the
+/// text samples are read from the media data.
+pub fn read(path: &Path) -> io::Result<Video> {
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+
+    let mut moov = None;
+    let mut fragments = Vec::new();
+    let mut position = 0;
+    while position + 8 <= length {
+        file.seek(SeekFrom::Start(position))?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        let kind = [header[4], header[5], header[6], header[7]];
+        let (size, header_length) =
+            match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+                // The size follows the type
+                1 => {
+                    let mut large = [0u8; 8];
+                    file.read_exact(&mut large)?;
+                    (u64::from_be_bytes(large), 16)
+                }
+                // The box runs until the end of the file
+                0 => (length - position, 8),
+                size => (u64::from(size), 8),
+            };
+        // Older QuickTime files can start with any box, `wide` or `mdat`
+        // for one, so only the type has to look like one
+        if position == 0
+            && !kind
+                .iter()
+                .all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+        {
+            return Err(invalid_data("not an MP4 file"));
+        }
+        if size < header_length || position.checked_add(size).is_none_or(|end| end > length) {
+            // Cut off at the end, like a file still downloading
+            break;
+        }
+
+        if &kind == b"moov" || &kind == b"moof" {
+            let mut payload = vec![0u8; (size - header_length) as usize];
+            file.read_exact(&mut payload)?;
+            if &kind == b"moov" {
+                moov = Some(payload);
+            } else {
+                fragments.push((position, payload));
+            }
+        }
+        position += size;
+    }
+    let moov = moov.ok_or_else(|| invalid_data("no movie box"))?;
+
+    let moov = Boxes(&moov);
+    let movie_timescale = moov
+        .child(b"mvhd")
+        .and_then(|mvhd| {
+            let offset = if mvhd.first() == Some(&1) { 20 } else { 12 };
+            read_u32(mvhd, offset)
+        })
+        .unwrap_or(1000);
+    let mut text_tracks = moov
+        .filter(|(kind, _)| kind == b"trak")
+        .filter_map(|(_, trak)| parse_track(trak, movie_timescale))
+        .collect::<Vec<TextTrack>>();
+    add_fragment_samples(&mut text_tracks, moov, &fragments);
+
+    let mut tracks = Vec::new();
+    let mut blocks = HashMap::new();
+    for text_track in text_tracks {
+        let to_ms = |time: u64| {
+            let time = i128::from(time) + i128::from(text_track.shift);
+            time * 1000 / i128::from(text_track.timescale.max(1))
+        };
+        let mut track_blocks = Vec::new();
+        for sample in &text_track.samples {
+            if sample.size > MAX_SAMPLE_SIZE {
+                continue;
+            }
+            let mut data = vec![0u8; sample.size as usize];
+            file.seek(SeekFrom::Start(sample.offset))?;
+            if file.read_exact(&mut data).is_err() {
+                break;
+            }
+            let start_time_ms = to_ms(sample.time);
+            let end_time_ms = to_ms(sample.time + sample.duration);
+            match text_track.track.codec {
+                Codec::TimedText => {
+                    if let Some(text) = tx3g_text(&data) {
+                        track_blocks.push(Block {
+                            start_time_ms,
+                            end_time_ms,
+                            data: text.into_bytes(),
+                            additional: None,
+                        });
+                    }
+                }
+                Codec::WebVtt => {
+                    // One sample has every cue shown at that time, `vtte` is a gap
+                    for (_, cue) in Boxes(&data).filter(|(kind, _)| kind == b"vttc") {
+                        let cue = Boxes(cue);
+                        track_blocks.push(Block {
+                            start_time_ms,
+                            end_time_ms,
+                            data: cue.child(b"payl").unwrap_or_default().to_vec(),
+                            additional: cue.child(b"sttg").map(<[u8]>::to_vec),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        blocks.insert(text_track.track.number, track_blocks);
+        tracks.push(text_track.track);
+    }
+
+    Ok(Video { tracks, blocks })
+}
+
+/// A `trak` box when it is a text track.
+fn parse_track(trak: &[u8], movie_timescale: u32) -> Option<TextTrack> {
+    let trak = Boxes(trak);
+    let mdia = Boxes(trak.child(b"mdia")?);
+    let handler = mdia.child(b"hdlr").and_then(|hdlr| hdlr.get(8..12))?;
+    if !matches!(handler, b"text" | b"sbtl" | b"subt") {
+        return None;
+    }
+
+    let tkhd = trak.child(b"tkhd")?;
+    let track_id = read_u32(tkhd, if tkhd.first() == Some(&1) { 20 } else { 12 })?;
+    let enabled = tkhd.get(3).is_some_and(|flags| flags & 1 != 0);
+
+    let mdhd = mdia.child(b"mdhd")?;
+    let (timescale, language) = if mdhd.first() == Some(&1) {
+        (read_u32(mdhd, 20)?, read_u16(mdhd, 32)?)
+    } else {
+        (read_u32(mdhd, 12)?, read_u16(mdhd, 20)?)
+    };
+    // Three letters of five bits each, counting from 0x60
+    let language = [10, 5, 0]
+        .iter()
+        .map(|shift| char::from(((language >> shift) & 0x1f) as u8 + 0x60))
+        .collect::<String>();
+
+    let stbl = Boxes(Boxes(mdia.child(b"minf")?).child(b"stbl")?);
+    let stsd = stbl.child(b"stsd")?;
+    let (entry_kind, entry) = Boxes(stsd.get(8..)?).next()?;
+    let (codec, codec_private) = match &entry_kind {
+        b"tx3g" => (Codec::TimedText, Vec::new()),
+        // The sample entry has 8 bytes before its boxes
+        b"wvtt" => (
+            Codec::WebVtt,
+            entry
+                .get(8..)
+                .and_then(|boxes| Boxes(boxes).child(b"vttC"))
+                .unwrap_or_default()
+                .to_vec(),
+        ),
+        b"stpp" => (Codec::Other(String::from("TTML")), Vec::new()),
+        b"c608" => (Codec::Other(String::from("CEA-608")), Vec::new()),
+        kind => (
+            Codec::Other(String::from_utf8_lossy(kind).into_owned()),
+            Vec::new(),
+        ),
+    };
+
+    let name = trak
+        .child(b"udta")
+        .and_then(|udta| Boxes(udta).child(b"name"))
+        .map(|name| {
+            String::from_utf8_lossy(name)
+                .trim_end_matches('\0')
+                .to_string()
+        })
+        .filter(|name| !name.is_empty());
+
+    Some(TextTrack {
+        track: Track {
+            number: u64::from(track_id),
+            codec,
+            codec_private,
+            language,
+            name,
+            default: enabled,
+            forced: false,
+        },
+        timescale: u64::from(timescale),
+        shift: edit_shift(trak, movie_timescale, timescale),
+        samples: sample_table(stbl).unwrap_or_default(),
+    })
+}
+
+/// Empty edits at the start of the edit list delay the track, and the
+/// first real edit can start inside the media.
+fn edit_shift(trak: Boxes, movie_timescale: u32, timescale: u32) -> i64 {
+    let Some(elst) = trak
+        .child(b"edts")
+        .and_then(|edts| Boxes(edts).child(b"elst"))
+    else {
+        return 0;
+    };
+    let version = elst.first().copied().unwrap_or_default();
+    let count = read_u32(elst, 4).unwrap_or_default() as usize;
+    let entry_size = if version == 1 { 20 } else { 12 };
+
+    let mut shift = 0i64;
+    for index in 0..count {
+        let offset = 8 + index * entry_size;
+        let (duration, media_time) = if version == 1 {
+            let (Some(duration), Some(media_time)) =
+                (read_u64(elst, offset), read_u64(elst, offset + 8))
+            else {
+                break;
+            };
+            (duration, media_time as i64)
+        } else {
+            let (Some(duration), Some(media_time)) =
+                (read_u32(elst, offset), read_u32(elst, offset + 4))
+            else {
+                break;
+            };
+            (u64::from(duration), i64::from(media_time as i32))
+        };
+
+        if media_time == -1 {
+            shift += (u128::from(duration) * u128::from(timescale)
+                / u128::from(movie_timescale.max(1))) as i64;
+        } else {
+            shift -= media_time;
+            break;
+        }
+    }
+    shift
+}
+
+/// The samples of a plain (not fragmented) file from its sample tables.
+fn sample_table(stbl: Boxes) -> Option<Vec<Sample>> {
+    let stsz = stbl.child(b"stsz")?;
+    let fixed_size = read_u32(stsz, 4)?;
+    let count = read_u32(stsz, 8)? as usize;
+    // The count of a broken file can be anything, the table of sizes
+    // cannot be longer than its box
+    let table_length = stsz.len().saturating_sub(12) / 4;
+    if fixed_size == 0 && table_length < count {
+        return None;
+    }
+    let mut sizes = (0..count).map(|index| match fixed_size {
+        0 => read_u32(stsz, 12 + index * 4).unwrap_or_default(),
+        size => size,
+    });
+
+    let chunk_offsets = if let Some(stco) = stbl.child(b"stco") {
+        let count = read_u32(stco, 4)? as usize;
+        (0..count)
+            .map(|index| read_u32(stco, 8 + index * 4).map(u64::from))
+            .collect::<Option<Vec<u64>>>()?
+    } else {
+        let co64 = stbl.child(b"co64")?;
+        let count = read_u32(co64, 4)? as usize;
+        (0..count)
+            .map(|index| read_u64(co64, 8 + index * 8))
+            .collect::<Option<Vec<u64>>>()?
+    };
+
+    // First chunk (counting from 1) and samples per chunk from there on
+    let stsc = stbl.child(b"stsc")?;
+    let runs = (0..read_u32(stsc, 4)? as usize)
+        .map(|index| {
+            let offset = 8 + index * 12;
+            Some((read_u32(stsc, offset)?, read_u32(stsc, offset + 4)?))
+        })
+        .collect::<Option<Vec<(u32, u32)>>>()?;
+
+    let mut samples = Vec::with_capacity(count.min(table_length));
+    for (chunk, chunk_offset) in chunk_offsets.into_iter().enumerate() {
+        let per_chunk = runs
+            .iter()
+            .rev()
+            .find(|(first_chunk, _)| *first_chunk as usize <= chunk + 1)
+            .map_or(0, |(_, per_chunk)| *per_chunk);
+        let mut offset = chunk_offset;
+        for size in sizes.by_ref().take(per_chunk as usize) {
+            samples.push(Sample {
+                offset,
+                size,
+                time: 0,
+                duration: 0,
+            });
+            offset += u64::from(size);
+        }
+    }
+
+    // Sample count and duration for runs of samples
+    let stts = stbl.child(b"stts")?;
+    let mut time = 0;
+    let mut samples_left = samples.iter_mut();
+    for index in 0..read_u32(stts, 4)? as usize {
+        let offset = 8 + index * 8;
+        let (Some(run), Some(duration)) = (read_u32(stts, offset), read_u32(stts, offset + 4))
+        else {
+            break;
+        };
+        for sample in samples_left.by_ref().take(run as usize) {
+            sample.time = time;
+            sample.duration = u64::from(duration);
+            time += u64::from(duration);
+        }
+    }
+
+    Some(samples)
+}
+
+/// Adds the samples of the movie fragments (`moof` boxes) to their tracks.
+fn add_fragment_samples(tracks: &mut [TextTrack], moov: Boxes, fragments: &[(u64, Vec<u8>)]) {
+    // Defaults per track from the `trex` boxes, duration then size
+    let defaults = moov
+        .child(b"mvex")
+        .map(|mvex| {
+            Boxes(mvex)
+                .filter(|(kind, _)| kind == b"trex")
+                .filter_map(|(_, trex)| {
+                    Some((
+                        read_u32(trex, 4)?,
+                        (read_u32(trex, 12)?, read_u32(trex, 16)?),
+                    ))
+                })
+                .collect::<HashMap<u32, (u32, u32)>>()
+        })
+        .unwrap_or_default();
+    let mut next_time = HashMap::new();
+
+    for (moof_offset, moof) in fragments {
+        for (_, traf) in Boxes(moof).filter(|(kind, _)| kind == b"traf") {
+            let traf = Boxes(traf);
+            let Some(tfhd) = traf.child(b"tfhd") else {
+                continue;
+            };
+            let Some(track_id) = read_u32(tfhd, 4) else {
+                continue;
+            };
+            let Some(track) = tracks
+                .iter_mut()
+                .find(|track| track.track.number == u64::from(track_id))
+            else {
+                continue;
+            };
+
+            let flags = read_u32(tfhd, 0).unwrap_or_default() & 0xffffff;
+            let (mut default_duration, mut default_size) =
+                defaults.get(&track_id).copied().unwrap_or_default();
+            let mut offset = 8;
+            let mut base_offset = *moof_offset;
+            if flags & 0x1 != 0 {
+                base_offset = read_u64(tfhd, offset).unwrap_or(base_offset);
+                offset += 8;
+            }
+            if flags & 0x2 != 0 {
+                offset += 4;
+            }
+            if flags & 0x8 != 0 {
+                default_duration = read_u32(tfhd, offset).unwrap_or(default_duration);
+                offset += 4;
+            }
+            if flags & 0x10 != 0 {
+                default_size = read_u32(tfhd, offset).unwrap_or(default_size);
+            }
+
+            let mut time = match traf.child(b"tfdt") {
+                Some(tfdt) if tfdt.first() == Some(&1) => read_u64(tfdt, 4).unwrap_or_default(),
+                Some(tfdt) => read_u32(tfdt, 4).map(u64::from).unwrap_or_default(),
+                None => next_time.get(&track_id).copied().unwrap_or_default(),
+            };
+            let mut data_offset = base_offset;
+
+            for (_, trun) in traf.filter(|(kind, _)| kind == b"trun") {
+                let flags = read_u32(trun, 0).unwrap_or_default() & 0xffffff;
+                let count = read_u32(trun, 4).unwrap_or_default();
+                let mut offset = 8;
+                if flags & 0x1 != 0 {
+                    let relative = read_u32(trun, offset).unwrap_or_default() as i32;
+                    data_offset = base_offset.saturating_add_signed(i64::from(relative));
+                    offset += 4;
+                }
+                if flags & 0x4 != 0 {
+                    offset += 4;
+                }
+                for _ in 0..count {
+                    let mut field = |present: bool, default: u32| {
+                        if !present {
+                            return Some(default);
+                        }
+                        let value = read_u32(trun, offset);
+                        offset += 4;
+                        value
+                    };
+                    let (Some(duration), Some(size), Some(_), Some(_)) = (
+                        field(flags & 0x100 != 0, default_duration),
+                        field(flags & 0x200 != 0, default_size),
+                        field(flags & 0x400 != 0, 0),
+                        field(flags & 0x800 != 0, 0),
+                    ) else {
+                        break;
+                    };
+                    track.samples.push(Sample {
+                        offset: data_offset,
+                        size,
+                        time,
+                        duration: u64::from(duration),
+                    });
+                    data_offset += u64::from(size);
+                    time += u64::from(duration);
+                }
+            }
+            next_time.insert(track_id, time);
+        }
+    }
+}
+
+/// The text of a 3GPP timed text sample, which starts with its length
+/// and can be followed by style boxes. `None` for an empty sample.
+fn tx3g_text(data: &[u8]) -> Option<String> {
+    let length = usize::from(read_u16(data, 0)?);
+    let text = data.get(2..2 + length)?;
+    if text.is_empty() {
+        return None;
+    }
+    let text = match text {
+        [0xfe, 0xff, rest @ ..] => {
+            let units = rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<u16>>();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).into_owned(),
+    };
+    Some(text.replace("\r\n", "\n"))
+}
+
+/// The boxes inside a box read into memory.
+#[derive(Clone, Copy)]
+struct Boxes<'a>(&'a [u8]);
+
+impl<'a> Boxes<'a> {
+    /// The payload of the first box of this type.
+    fn child(mut self, wanted: &[u8; 4]) -> Option<&'a [u8]> {
+        self.find(|(kind, _)| kind == wanted)
+            .map(|(_, payload)| payload)
+    }
+}
+
+impl<'a> Iterator for Boxes<'a> {
+    type Item = ([u8; 4], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let size = read_u32(self.0, 0)?;
+        let kind = self.0.get(4..8)?.try_into().ok()?;
+        let (start, end) = match size {
+            1 => (16, usize::try_from(read_u64(self.0, 8)?).ok()?),
+            0 => (8, self.0.len()),
+            size => (8, size as usize),
+        };
+        let payload = self.0.get(start..end)?;
+        self.0 = &self.0[end..];
+        Some((kind, payload))
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        [&(payload.len() as u32 + 8).to_be_bytes(), kind, payload].concat()
+    }
+
+    /// A box with a version and flags, followed by its fields.
+    fn full_box(kind: &[u8; 4], flags: u32, fields: &[u32]) -> Vec<u8> {
+        let payload = std::iter::once(flags)
+            .chain(fields.iter().copied())
+            .flat_map(u32::to_be_bytes)
+            .collect::<Vec<u8>>();
+        mp4_box(kind, &payload)
+    }
+
+    fn read_bytes(name: &str, data: &[u8]) -> io::Result<Video> {
+        let path: PathBuf = env::temp_dir().join(format!("{}-{name}.mp4", std::process::id()));
+        fs::write(&path, data)?;
+        let video = read(&path);
+        fs::remove_file(&path)?;
+        video
+    }
+
+    fn tx3g_sample(text: &[u8]) -> Vec<u8> {
+        [&(text.len() as u16).to_be_bytes(), text].concat()
+    }
+
+    /// A tx3g track numbered 1 with a timescale of 1000, `stbl` holds the
+    /// sample tables.
+    fn text_trak(stbl: &[u8]) -> Vec<u8> {
+        let stsd = [0u32.to_be_bytes(), 1u32.to_be_bytes()].concat();
+        let stsd = mp4_box(b"stsd", &[stsd, mp4_box(b"tx3g", &[0; 8])].concat());
+        let stbl = mp4_box(b"stbl", &[stsd.as_slice(), stbl].concat());
+        // "und" in three five bit letters
+        let mdhd = full_box(b"mdhd", 0, &[0, 0, 1000, 0, 0x55c4_0000]);
+        let hdlr = mp4_box(b"hdlr", &[[0; 8].as_slice(), b"text", &[0; 13]].concat());
+        let mdia = mp4_box(b"mdia", &[mdhd, hdlr, mp4_box(b"minf", &stbl)].concat());
+        let tkhd = full_box(b"tkhd", 1, &[0, 0, 1, 0, 0]);
+        mp4_box(b"trak", &[tkhd, mdia].concat())
+    }
+
+    fn moov(boxes: &[Vec<u8>]) -> Vec<u8> {
+        let mvhd = full_box(b"mvhd", 0, &[0, 0, 1000, 0]);
+        mp4_box(b"moov", &[&[mvhd], boxes].concat().concat())
+    }
+
+    fn times_and_text(video: &Video) -> Vec<(i128, i128, String)> {
+        video
+            .blocks(1)
+            .iter()
+            .map(|block| {
+                let text = String::from_utf8(block.data.clone()).unwrap();
+                (block.start_time_ms, block.end_time_ms, text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sample_tables() {
+        // Older QuickTime files can start with the media data
+        let samples = [
+            tx3g_sample(b"Hello"),
+            tx3g_sample(b""),
+            tx3g_sample(b"World"),
+        ];
+        let mdat = mp4_box(b"mdat", &samples.concat());
+        let sizes = samples.iter().map(|sample| sample.len() as u32);
+        let stbl = [
+            full_box(
+                b"stsz",
+                0,
+                &[[0, 3].as_slice(), &sizes.collect::<Vec<u32>>()].concat(),
+            ),
+            // One chunk right after the `mdat` header
+            full_box(b"stco", 0, &[1, 8]),
+            full_box(b"stsc", 0, &[1, 1, 3, 1]),
+            // 1.5 s, then 0.5 s twice
+            full_box(b"stts", 0, &[2, 1, 1500, 2, 500]),
+        ]
+        .concat();
+        let video = read_bytes("tables", &[mdat, moov(&[text_trak(&stbl)])].concat()).unwrap();
+
+        assert_eq!(video.tracks[0].codec, Codec::TimedText);
+        assert_eq!(video.tracks[0].language, "und");
+        assert_eq!(
+            times_and_text(&video),
+            [
+                (0, 1500, String::from("Hello")),
+                (2000, 2500, String::from("World")),
+            ]
+        );
+    }
+
+    #[test]
+    fn fragments_based_on_their_moof() {
+        let empty_tables = [
+            full_box(b"stsz", 0, &[0, 0]),
+            full_box(b"stco", 0, &[0]),
+            full_box(b"stsc", 0, &[0]),
+            full_box(b"stts", 0, &[0]),
+        ]
+        .concat();
+        // Track 1, sample description 1, 1 s long samples of 7 bytes
+        let trex = full_box(b"trex", 0, &[1, 1, 1000, 7, 0]);
+        let moov = moov(&[text_trak(&empty_tables), mp4_box(b"mvex", &trex)]);
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\0\0");
+
+        let samples = [tx3g_sample(b"Hello"), tx3g_sample(b"World")].concat();
+        let moof = |data_offset: u32| {
+            // default-base-is-moof
+            let tfhd = full_box(b"tfhd", 0x02_0000, &[1]);
+            // Version 1 with a 64 bit time of 10 s
+            let tfdt = full_box(b"tfdt", 0x0100_0000, &[0, 10_000]);
+            // A data offset and the default sizes and durations
+            let trun = full_box(b"trun", 0x1, &[2, data_offset]);
+            let traf = mp4_box(b"traf", &[tfhd, tfdt, trun].concat());
+            mp4_box(b"moof", &[full_box(b"mfhd", 0, &[1]), traf].concat())
+        };
+        // The samples start right after the `mdat` header that follows
+        let moof = moof(moof(0).len() as u32 + 8);
+        let mdat = mp4_box(b"mdat", &samples);
+        let video = read_bytes("fragments", &[ftyp, moov, moof, mdat].concat()).unwrap();
+
+        assert_eq!(
+            times_and_text(&video),
+            [
+                (10_000, 11_000, String::from("Hello")),
+                (11_000, 12_000, String::from("World")),
+            ]
+        );
+    }
+
+    #[test]
+    fn tx3g_text_with_a_byte_order_mark() {
+        let sample = [tx3g_sample(b"\xfe\xff\0H\0i"), mp4_box(b"styl", &[0; 2])].concat();
+        assert_eq!(tx3g_text(&sample).as_deref(), Some("Hi"));
+        assert_eq!(
+            tx3g_text(&tx3g_sample(b"One\r\nTwo")).as_deref(),
+            Some("One\nTwo")
+        );
+        assert_eq!(tx3g_text(&tx3g_sample(b"")), None);
+    }
+
+    #[test]
+    fn sample_count_larger_than_the_table() {
+        // Each would be gigabytes of sizes if the count was trusted
+        let tables = |stsz: Vec<u8>| {
+            let stbl = [
+                stsz,
+                full_box(b"stco", 0, &[1, 8]),
+                full_box(b"stsc", 0, &[1, 1, 2, 1]),
+                full_box(b"stts", 0, &[1, u32::MAX, 1]),
+            ]
+            .concat();
+            sample_table(Boxes(&stbl)).map(|samples| samples.len())
+        };
+        assert_eq!(tables(full_box(b"stsz", 0, &[0, u32::MAX, 5])), None);
+        // With a fixed size only the chunks limit the samples
+        assert_eq!(tables(full_box(b"stsz", 0, &[5, u32::MAX])), Some(2));
+    }
+
+    #[test]
+    fn box_larger_than_any_file() {
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\0\0");
+        // A 64 bit size that overflows when added to its position
+        let broken = [
+            1u32.to_be_bytes().as_slice(),
+            b"free",
+            &u64::MAX.to_be_bytes(),
+        ]
+        .concat();
+        let data = [ftyp, moov(&[]), broken].concat();
+        let video = read_bytes("overflow", &data).unwrap();
+        assert!(video.tracks.is_empty());
+    }
+}
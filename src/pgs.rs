@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+
+use iced::{Point, Size};
+
+use crate::subtitle::{Bitmap, Subtitle};
+
+/// How long the last subtitle stays when nothing clears it.
+const LAST_SUBTITLE_MS: i128 = 5000;
+
+const PALETTE_SEGMENT: u8 = 0x14;
+const OBJECT_SEGMENT: u8 = 0x15;
+const PRESENTATION_SEGMENT: u8 = 0x16;
+const END_SEGMENT: u8 = 0x80;
+
+/// Checks for the `PG` magic every segment of a `.sup` file starts with.
+pub fn is_pgs(data: &[u8]) -> bool {
+    data.starts_with(b"PG")
+}
+
+/// An object placed on screen by a presentation segment.
+struct CompositionObject {
+    object_id: u16,
+    x: u16,
+    y: u16,
+}
+
+/// A presentation segment, which shows its objects until the next one.
+struct Composition {
+    time_ms: i128,
+    video_size: Size,
+    palette_id: u8,
+    objects: Vec<CompositionObject>,
+}
+
+/// The decoded objects of a presentation, with their top left corner.
+struct DisplaySet {
+    time_ms: i128,
+    video_size: Size,
+    bitmaps: Vec<(Point, Bitmap)>,
+}
+
+/// Object data is split over segments when it is large.
+#[derive(Default)]
+struct Object {
+    width: u16,
+    height: u16,
+    rle: Vec<u8>,
+}
+
+/// Parses a Blu-ray `.sup` (PGS) file into bitmap subtitles. Every object
+/// of a display set becomes its own subtitle, placed where the disc puts it.
+pub fn parse(data: &[u8]) -> Vec<Subtitle> {
+    let mut palettes: HashMap<u8, [[u8; 4]; 256]> = HashMap::new();
+    let mut objects: HashMap<u16, Object> = HashMap::new();
+    let mut composition: Option<Composition> = None;
+    // The end time of a display set is only known when the next one starts
+    let mut shown: Option<DisplaySet> = None;
+    let mut subtitles = Vec::new();
+    // A PTS counts 90 kHz ticks in 33 bits and the header only keeps the
+    // low 32, so they go back to 0 every 13 hours or so
+    let mut last_pts = None;
+    let mut wraps = 0;
+
+    let mut rest = data;
+    while rest.len() >= 13 && is_pgs(rest) {
+        let pts = u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]);
+        let kind = rest[10];
+        let size = usize::from(u16::from_be_bytes([rest[11], rest[12]]));
+        let Some(segment) = rest.get(13..13 + size) else {
+            break;
+        };
+        rest = &rest[13 + size..];
+
+        match kind {
+            PRESENTATION_SEGMENT if segment.len() >= 11 => {
+                if last_pts.is_some_and(|last: u32| pts < last && last - pts > 1 << 31) {
+                    wraps += 1;
+                }
+                last_pts = Some(pts);
+                let time_ms = ((wraps << 32) + i128::from(pts)) / 90;
+                // Whatever was on screen goes away when the next set starts
+                if let Some(display_set) = shown.take() {
+                    subtitles.extend(to_subtitles(display_set, time_ms));
+                }
+
+                // Composition state 0x80 starts a new epoch without old objects
+                if segment[7] & 0x80 != 0 {
+                    objects.clear();
+                }
+                let count = usize::from(segment[10]);
+                let mut entries = &segment[11..];
+                let mut composition_objects = Vec::new();
+                for _ in 0..count {
+                    if entries.len() < 8 {
+                        break;
+                    }
+                    composition_objects.push(CompositionObject {
+                        object_id: u16::from_be_bytes([entries[0], entries[1]]),
+                        x: u16::from_be_bytes([entries[4], entries[5]]),
+                        y: u16::from_be_bytes([entries[6], entries[7]]),
+                    });
+                    // 0x40 marks forced subtitles, 0x80 a cropping rectangle
+                    let cropped = entries[3] & 0x80 != 0;
+                    entries = entries
+                        .get(if cropped { 16 } else { 8 }..)
+                        .unwrap_or_default();
+                }
+
+                composition = Some(Composition {
+                    time_ms,
+                    video_size: Size::new(
+                        f32::from(u16::from_be_bytes([segment[0], segment[1]])),
+                        f32::from(u16::from_be_bytes([segment[2], segment[3]])),
+                    ),
+                    palette_id: segment[9],
+                    objects: composition_objects,
+                });
+            }
+            PALETTE_SEGMENT if segment.len() >= 2 => {
+                let palette = palettes.entry(segment[0]).or_insert([[0; 4]; 256]);
+                for entry in segment[2..].chunks_exact(5) {
+                    palette[usize::from(entry[0])] =
+                        ycrcb_to_rgba(entry[1], entry[2], entry[3], entry[4]);
+                }
+            }
+            OBJECT_SEGMENT if segment.len() >= 4 => {
+                let object_id = u16::from_be_bytes([segment[0], segment[1]]);
+                let object = objects.entry(object_id).or_default();
+                // The first part of an object carries its size
+                if segment[3] & 0x80 != 0 && segment.len() >= 11 {
+                    object.width = u16::from_be_bytes([segment[7], segment[8]]);
+                    object.height = u16::from_be_bytes([segment[9], segment[10]]);
+                    object.rle = segment[11..].to_vec();
+                } else {
+                    object.rle.extend_from_slice(&segment[4..]);
+                }
+            }
+            END_SEGMENT => {
+                let Some(composition) = composition.take() else {
+                    continue;
+                };
+                let Some(palette) = palettes.get(&composition.palette_id) else {
+                    continue;
+                };
+                let bitmaps = composition
+                    .objects
+                    .iter()
+                    .filter_map(|placed| {
+                        let object = objects.get(&placed.object_id)?;
+                        let corner = Point::new(f32::from(placed.x), f32::from(placed.y));
+                        let pixels = decode_object(object, palette)?;
+                        let bitmap = Bitmap::new(object.width.into(), object.height.into(), pixels);
+                        Some((corner, bitmap))
+                    })
+                    .collect::<Vec<(Point, Bitmap)>>();
+                if !bitmaps.is_empty() {
+                    shown = Some(DisplaySet {
+                        time_ms: composition.time_ms,
+                        video_size: composition.video_size,
+                        bitmaps,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(display_set) = shown {
+        let end_time_ms = display_set.time_ms + LAST_SUBTITLE_MS;
+        subtitles.extend(to_subtitles(display_set, end_time_ms));
+    }
+
+    subtitles
+}
+
+fn to_subtitles(display_set: DisplaySet, end_time_ms: i128) -> impl Iterator<Item = Subtitle> {
+    display_set
+        .bitmaps
+        .into_iter()
+        .map(move |(corner, bitmap)| {
+            Subtitle::bitmap(
+                display_set.time_ms,
+                end_time_ms,
+                bitmap,
+                corner,
+                display_set.video_size,
+            )
+        })
+}
+
+/// Decodes the run length encoded pixels of an object into RGBA, see the
+/// `ObjectDataSegment` description of the Blu-ray format.
+fn decode_object(object: &Object, palette: &[[u8; 4]; 256]) -> Option<Vec<u8>> {
+    let width = usize::from(object.width);
+    let height = usize::from(object.height);
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut pixels = vec![0u8; width * height * 4];
+    let (mut x, mut y) = (0, 0);
+    let mut bytes = object.rle.iter().copied();
+
+    while y < height {
+        let Some(first) = bytes.next() else {
+            break;
+        };
+        let (length, color) = if first != 0 {
+            (1, first)
+        } else {
+            let flags = bytes.next()?;
+            if flags == 0 {
+                x = 0;
+                y += 1;
+                continue;
+            }
+            let mut length = usize::from(flags & 0x3f);
+            if flags & 0x40 != 0 {
+                length = length << 8 | usize::from(bytes.next()?);
+            }
+            let color = if flags & 0x80 != 0 { bytes.next()? } else { 0 };
+            (length, color)
+        };
+
+        let rgba = palette[usize::from(color)];
+        for _ in 0..length {
+            if x < width {
+                let index = (y * width + x) * 4;
+                pixels[index..index + 4].copy_from_slice(&rgba);
+            }
+            x += 1;
+        }
+    }
+
+    Some(pixels)
+}
+
+/// PGS palettes are BT.709 YCrCb with alpha.
+fn ycrcb_to_rgba(y: u8, cr: u8, cb: u8, alpha: u8) -> [u8; 4] {
+    let y = f32::from(y);
+    let cr = f32::from(cr) - 128.0;
+    let cb = f32::from(cb) - 128.0;
+    let channel = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    [
+        channel(y + 1.5748 * cr),
+        channel(y - 0.1873 * cb - 0.4681 * cr),
+        channel(y + 1.8556 * cb),
+        alpha,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subtitle::Position;
+
+    use super::*;
+
+    #[test]
+    fn run_length_decoding() {
+        let mut palette = [[0; 4]; 256];
+        palette[1] = [255, 0, 0, 255];
+        palette[2] = [0, 0, 255, 255];
+        let object = Object {
+            width: 4,
+            height: 2,
+            rle: [
+                // One pixel of colour 1, then 3 of colour 2, end of line
+                [1, 0, 0x83, 2, 0, 0].as_slice(),
+                // 4 transparent pixels with a long length, end of line
+                &[0, 0x40, 4, 0, 0],
+            ]
+            .concat(),
+        };
+        let pixels = decode_object(&object, &palette).unwrap();
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        assert_eq!(pixels[..16], [red, blue, blue, blue].concat());
+        assert_eq!(pixels[16..], [0; 16]);
+    }
+
+    #[test]
+    fn runs_stop_at_the_edge() {
+        let mut palette = [[0; 4]; 256];
+        palette[5] = [1, 2, 3, 4];
+        let object = Object {
+            width: 2,
+            height: 1,
+            rle: vec![0, 0x85, 5, 0, 0],
+        };
+        assert_eq!(
+            decode_object(&object, &palette).unwrap(),
+            [[1, 2, 3, 4]; 2].concat()
+        );
+    }
+
+    fn segment(pts: u32, kind: u8, payload: &[u8]) -> Vec<u8> {
+        [
+            b"PG".as_slice(),
+            &pts.to_be_bytes(),
+            // Decoding time, not used
+            &[0; 4],
+            &[kind],
+            &(payload.len() as u16).to_be_bytes(),
+            payload,
+        ]
+        .concat()
+    }
+
+    /// A display set showing one 2x1 object at (100, 50) of a 1080p video.
+    fn display_set(pts: u32) -> Vec<u8> {
+        let composition = [
+            1920u16.to_be_bytes().as_slice(),
+            &1080u16.to_be_bytes(),
+            // Frame rate, number, epoch start, no palette update, palette 0, one object
+            &[0x10, 0, 1, 0x80, 0, 0, 1],
+            // Object 0 in window 0, not cropped
+            &[0, 0, 0, 0],
+            &100u16.to_be_bytes(),
+            &50u16.to_be_bytes(),
+        ]
+        .concat();
+        // Palette 0, version 0, entry 1 is opaque white
+        let palette = [0, 0, 1, 235, 128, 128, 255];
+        let object = [
+            // Object 0, version 0, first and last part of the data
+            [0, 0, 0, 0xc0].as_slice(),
+            &[0, 0, 8],
+            &2u16.to_be_bytes(),
+            &1u16.to_be_bytes(),
+            // Two pixels of colour 1, end of line
+            &[1, 1, 0, 0],
+        ]
+        .concat();
+        [
+            segment(pts, PRESENTATION_SEGMENT, &composition),
+            segment(pts, PALETTE_SEGMENT, &palette),
+            segment(pts, OBJECT_SEGMENT, &object),
+            segment(pts, END_SEGMENT, &[]),
+        ]
+        .concat()
+    }
+
+    /// A display set without objects, which clears the screen.
+    fn clear(pts: u32) -> Vec<u8> {
+        let composition = [
+            1920u16.to_be_bytes().as_slice(),
+            &1080u16.to_be_bytes(),
+            &[0x10, 0, 2, 0, 0, 0, 0],
+        ]
+        .concat();
+        [
+            segment(pts, PRESENTATION_SEGMENT, &composition),
+            segment(pts, END_SEGMENT, &[]),
+        ]
+        .concat()
+    }
+
+    fn times(subtitles: &[Subtitle]) -> Vec<(i128, i128)> {
+        subtitles
+            .iter()
+            .map(|subtitle| (subtitle.start_time_ms, subtitle.end_time_ms))
+            .collect()
+    }
+
+    #[test]
+    fn one_display_set() {
+        let subtitles = parse(&[display_set(180_000), clear(360_000)].concat());
+        assert_eq!(times(&subtitles), [(2000, 4000)]);
+        let placement = subtitles[0].placement.unwrap();
+        assert_eq!(
+            placement.position,
+            Some(Position::Fixed(Point::new(100.0, 50.0)))
+        );
+        assert_eq!(placement.resolution, Size::new(1920.0, 1080.0));
+        assert!(subtitles[0].bitmap.is_some());
+
+        // Without anything clearing it the last one stays a while
+        let subtitles = parse(&display_set(180_000));
+        assert_eq!(times(&subtitles), [(2000, 2000 + LAST_SUBTITLE_MS)]);
+    }
+
+    #[test]
+    fn timestamps_wrap_around() {
+        let first = u32::MAX - 90 * 999;
+        let subtitles = parse(&[display_set(first), clear(90_000), display_set(180_000)].concat());
+        let wrapped = 1i128 << 32;
+        assert_eq!(
+            times(&subtitles),
+            [
+                (i128::from(first) / 90, (wrapped + 90_000) / 90),
+                (
+                    (wrapped + 180_000) / 90,
+                    (wrapped + 180_000) / 90 + LAST_SUBTITLE_MS
+                ),
+            ]
+        );
+    }
+}
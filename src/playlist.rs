@@ -0,0 +1,195 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+};
+
+use crate::subtitle::Subtitle;
+
+/// Subtitle files of episodes that play one after another, for when the
+/// whole season is one continuous video.
+pub struct Playlist {
+    pub episodes: Vec<Episode>,
+    pub current: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Episode {
+    pub path: PathBuf,
+    /// When the last subtitle of the episode ends, 0 until it has been read.
+    pub length_ms: u128,
+    /// Where the episode starts in the playback, `None` starts it when the
+    /// one before it ends.
+    pub offset_ms: Option<i128>,
+}
+
+impl Playlist {
+    /// Puts the files in episode order, see [`natural_cmp`].
+    pub fn new(mut episodes: Vec<Episode>) -> Self {
+        episodes.sort_by(|a, b| {
+            let (a, b) = (file_name(&a.path), file_name(&b.path));
+            natural_cmp(&a, &b).then_with(|| a.cmp(&b))
+        });
+        Playlist {
+            episodes,
+            current: 0,
+        }
+    }
+
+    pub fn current(&self) -> &Episode {
+        &self.episodes[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Episode {
+        &mut self.episodes[self.current]
+    }
+
+    /// Where an episode starts in the playback.
+    pub fn offset_ms(&self, index: usize) -> i128 {
+        self.episodes[..=index]
+            .iter()
+            .fold((0, 0), |(offset_ms, length_ms), episode| {
+                (
+                    episode.offset_ms.unwrap_or(offset_ms + length_ms),
+                    episode.length_ms as i128,
+                )
+            })
+            .0
+    }
+
+    /// Where the episode after the current one starts, if there is one.
+    /// `None` as well while the current episode has no length, it has not
+    /// been read (yet) and would end right where it starts.
+    pub fn next_offset_ms(&self) -> Option<i128> {
+        (self.current + 1 < self.episodes.len() && self.current().length_ms > 0)
+            .then(|| self.offset_ms(self.current + 1))
+    }
+}
+
+impl fmt::Display for Episode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", file_name(&self.path))
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Compares names the way people count, so "Episode 9" comes before
+/// "Episode 10". Runs of digits are compared by their value, the rest
+/// ignoring case.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let order = match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (digits(&mut a), digits(&mut b));
+                let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(x), Some(y)) => {
+                a.next();
+                b.next();
+                x.to_lowercase().cmp(y.to_lowercase())
+            }
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+fn digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+    digits
+}
+
+/// When the last of the subtitles ends.
+pub fn length_ms(subtitles: &[Subtitle]) -> u128 {
+    subtitles
+        .iter()
+        .map(|subtitle| subtitle.end_time_ms)
+        .max()
+        .map_or(0, |end_ms| end_ms.max(0) as u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(name: &str, length_ms: u128, offset_ms: Option<i128>) -> Episode {
+        Episode {
+            path: PathBuf::from(name),
+            length_ms,
+            offset_ms,
+        }
+    }
+
+    #[test]
+    fn numbers_sort_by_value() {
+        let mut names = vec!["Ep10.srt", "ep9.srt", "Ep1.srt", "ep02.srt", "Ep2b.srt"];
+        names.sort_by(|a, b| natural_cmp(a, b).then_with(|| a.cmp(b)));
+        assert_eq!(
+            names,
+            ["Ep1.srt", "ep02.srt", "Ep2b.srt", "ep9.srt", "Ep10.srt"]
+        );
+    }
+
+    #[test]
+    fn leading_zeros_and_case_are_equal() {
+        assert_eq!(natural_cmp("Show 007", "show 7"), Ordering::Equal);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("b", "A"), Ordering::Greater);
+    }
+
+    #[test]
+    fn new_puts_episodes_in_order() {
+        let playlist = Playlist::new(vec![
+            episode("/season/E10.srt", 0, None),
+            episode("/season/E2.srt", 0, None),
+            episode("/season/E1.srt", 0, None),
+        ]);
+        let names = playlist
+            .episodes
+            .iter()
+            .map(Episode::to_string)
+            .collect::<Vec<String>>();
+        assert_eq!(names, ["E1.srt", "E2.srt", "E10.srt"]);
+    }
+
+    #[test]
+    fn episodes_follow_each_other() {
+        let playlist = Playlist::new(vec![
+            episode("1", 1000, Some(500)),
+            episode("2", 2000, None),
+            episode("3", 3000, Some(10_000)),
+            episode("4", 0, None),
+        ]);
+        let offsets = (0..4).map(|index| playlist.offset_ms(index));
+        assert_eq!(offsets.collect::<Vec<i128>>(), [500, 1500, 10_000, 13_000]);
+    }
+
+    #[test]
+    fn no_next_episode_without_a_length() {
+        let mut playlist = Playlist::new(vec![episode("1", 0, None), episode("2", 0, None)]);
+        assert_eq!(playlist.next_offset_ms(), None);
+
+        playlist.current_mut().length_ms = 1000;
+        assert_eq!(playlist.next_offset_ms(), Some(1000));
+
+        playlist.current = 1;
+        assert_eq!(playlist.next_offset_ms(), None);
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt;
+
+/// A cue read by the lenient reader, the text still has its markup.
+pub struct Cue {
+    pub start_time_ms: i128,
+    pub end_time_ms: i128,
+    pub text: String,
+}
+
+/// Something the lenient reader had to fix, or leave out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repair {
+    /// Counts from 1.
+    pub line: usize,
+    pub skipped: bool,
+    pub description: String,
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = if self.skipped { "skipped" } else { "repaired" };
+        write!(f, "line {}: {action}, {}", self.line, self.description)
+    }
+}
+
+/// Reads a broken SRT file, keeping every cue that has a usable timing
+/// line. Used when the strict parser gives up on a file.
+///
+/// Blank lines between cues and the cue numbers are optional here, a cue
+/// ends at the next timing line (or number right before one).
+pub fn parse(data: &str) -> (Vec<Cue>, Vec<Repair>) {
+    let mut cues = Vec::new();
+    let mut repairs = Vec::new();
+    let repair = |line: usize, skipped: bool, description: String| Repair {
+        line: line + 1,
+        skipped,
+        description,
+    };
+
+    let data = data.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines = Vec::new();
+    for (number, line) in data.lines().enumerate() {
+        if number > 0 && line.contains('\u{feff}') {
+            repairs.push(repair(
+                number,
+                false,
+                String::from("removed a byte order mark in the middle of the file"),
+            ));
+        }
+        lines.push(line.replace('\u{feff}', ""));
+    }
+
+    let is_timing = |line: &str| line.contains("-->");
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index].trim();
+        if !is_timing(line) {
+            if !line.is_empty() && line.parse::<i64>().is_err() {
+                repairs.push(repair(
+                    index,
+                    true,
+                    format!("text outside of a subtitle: {line}"),
+                ));
+            }
+            index += 1;
+            continue;
+        }
+
+        let timing_line = index;
+        // The text runs until a blank line, the next timing line or the
+        // cue number in front of it
+        let mut text = Vec::new();
+        index += 1;
+        while index < lines.len() {
+            let line = lines[index].trim_end();
+            if line.trim().is_empty() {
+                break;
+            }
+            let next_is_timing = lines.get(index + 1).is_some_and(|next| is_timing(next));
+            if is_timing(line) || (next_is_timing && line.trim().parse::<i64>().is_ok()) {
+                repairs.push(repair(
+                    index,
+                    false,
+                    String::from("missing blank line between subtitles"),
+                ));
+                break;
+            }
+            text.push(line);
+            index += 1;
+        }
+
+        let Some((start_time_ms, end_time_ms, fixes)) = parse_timing(line) else {
+            repairs.push(repair(
+                timing_line,
+                true,
+                format!("unreadable timing: {line}"),
+            ));
+            continue;
+        };
+        for fix in fixes {
+            repairs.push(repair(timing_line, false, fix));
+        }
+        if end_time_ms < start_time_ms {
+            repairs.push(repair(
+                timing_line,
+                true,
+                String::from("the subtitle ends before it starts"),
+            ));
+            continue;
+        }
+        if text.is_empty() {
+            repairs.push(repair(
+                timing_line,
+                true,
+                String::from("subtitle without text"),
+            ));
+            continue;
+        }
+
+        cues.push(Cue {
+            start_time_ms,
+            end_time_ms,
+            text: text.join("\n"),
+        });
+    }
+    repairs.sort_by_key(|repair| repair.line);
+
+    (cues, repairs)
+}
+
+/// Whether a timing line has a time before the start, which only this
+/// reader keeps.
+pub fn has_negative_times(data: &str) -> bool {
+    data.lines()
+        .filter_map(|line| line.split_once("-->"))
+        .any(|(start, end)| {
+            start.trim_start().starts_with('-') || end.trim_start().starts_with('-')
+        })
+}
+
+/// `00:00:01,000 --> 00:00:02,000`, also with the fixes that were needed.
+fn parse_timing(line: &str) -> Option<(i128, i128, Vec<String>)> {
+    let (start, end) = line.split_once("-->")?;
+    // Players ignore anything after the end time, like positions
+    let end = end.split_whitespace().next()?;
+
+    let mut fixes = Vec::new();
+    let start_time_ms = parse_timestamp(start.trim(), &mut fixes)?;
+    let end_time_ms = parse_timestamp(end, &mut fixes)?;
+    fixes.dedup();
+
+    Some((start_time_ms, end_time_ms, fixes))
+}
+
+/// `hh:mm:ss,mmm`, accepting `.`, `;` or `:` before the milliseconds,
+/// missing hours, short fractions and negative times.
+fn parse_timestamp(input: &str, fixes: &mut Vec<String>) -> Option<i128> {
+    let (negative, input) = match input.strip_prefix('-') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, input),
+    };
+
+    let (clock, fraction) = match input.rsplit_once([',', '.', ';']) {
+        Some((clock, fraction)) => {
+            let separator = char::from(input.as_bytes()[clock.len()]);
+            if separator != ',' {
+                fixes.push(format!("`{separator}` used instead of `,` in a time"));
+            }
+            (clock, fraction)
+        }
+        None => {
+            // 00:00:01:000, the last colon is the comma
+            let parts = input.split(':').count();
+            match input.rsplit_once(':') {
+                Some((clock, fraction)) if parts == 4 => {
+                    fixes.push(String::from("`:` used instead of `,` in a time"));
+                    (clock, fraction)
+                }
+                _ => (input, "0"),
+            }
+        }
+    };
+
+    let parts = clock
+        .split(':')
+        .map(|part| part.trim().parse::<i128>().ok())
+        .collect::<Option<Vec<i128>>>()?;
+    let (hh, mm, ss) = match parts[..] {
+        [hh, mm, ss] => (hh, mm, ss),
+        [mm, ss] => {
+            fixes.push(String::from("time without hours"));
+            (0, mm, ss)
+        }
+        _ => return None,
+    };
+
+    let fraction = fraction.trim();
+    if fraction.is_empty() || fraction.len() > 3 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // "5" and "50" are both half a second
+    let ms = format!("{fraction:0<3}").parse::<i128>().ok()?;
+
+    let time = hh * 3600000 + mm * 60000 + ss * 1000 + ms;
+    Some(if negative { -time } else { time })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(cues: &[Cue]) -> Vec<(i128, i128)> {
+        cues.iter()
+            .map(|cue| (cue.start_time_ms, cue.end_time_ms))
+            .collect()
+    }
+
+    fn repaired(line: usize, description: &str) -> Repair {
+        Repair {
+            line,
+            skipped: false,
+            description: String::from(description),
+        }
+    }
+
+    fn skipped(line: usize, description: &str) -> Repair {
+        Repair {
+            skipped: true,
+            ..repaired(line, description)
+        }
+    }
+
+    #[test]
+    fn missing_index() {
+        let (cues, repairs) = parse(
+            "00:00:01,000 --> 00:00:02,000\nFirst\n\n\
+             2\n00:00:03,000 --> 00:00:04,000\nSecond\n",
+        );
+        assert_eq!(times(&cues), [(1000, 2000), (3000, 4000)]);
+        assert_eq!(cues[0].text, "First");
+        assert_eq!(repairs, []);
+    }
+
+    #[test]
+    fn separators_before_the_milliseconds() {
+        let (cues, repairs) = parse(
+            "1\n00:00:01,000 --> 00:00:02.500\nA\n\n\
+             2\n00:00:03;000 --> 00:00:04;250\nB\n\n\
+             3\n00:00:05:000 --> 00:00:06:000\nC\n",
+        );
+        assert_eq!(times(&cues), [(1000, 2500), (3000, 4250), (5000, 6000)]);
+        assert_eq!(
+            repairs,
+            [
+                repaired(2, "`.` used instead of `,` in a time"),
+                repaired(6, "`;` used instead of `,` in a time"),
+                repaired(10, "`:` used instead of `,` in a time"),
+            ]
+        );
+    }
+
+    #[test]
+    fn swapped_times_are_skipped() {
+        let (cues, repairs) = parse(
+            "1\n00:00:05,000 --> 00:00:04,000\nBackwards\n\n\
+             2\n00:00:06,000 --> 00:00:07,000\nFine\n",
+        );
+        assert_eq!(times(&cues), [(6000, 7000)]);
+        assert_eq!(repairs, [skipped(2, "the subtitle ends before it starts")]);
+    }
+
+    #[test]
+    fn overlapping_times_and_numbers_are_kept() {
+        let (cues, repairs) = parse(
+            "1\n00:00:01,000 --> 00:00:05,000\nLong\n\n\
+             1\n00:00:02,000 --> 00:00:03,000\nInside\n",
+        );
+        assert_eq!(times(&cues), [(1000, 5000), (2000, 3000)]);
+        assert_eq!(repairs, []);
+    }
+
+    #[test]
+    fn missing_blank_line() {
+        let (cues, repairs) = parse(
+            "1\n00:00:01,000 --> 00:00:02,000\nFirst\n\
+             2\n00:00:03,000 --> 00:00:04,000\nSecond\n\
+             00:00:05,000 --> 00:00:06,000\nThird\n",
+        );
+        assert_eq!(times(&cues), [(1000, 2000), (3000, 4000), (5000, 6000)]);
+        assert_eq!(cues[1].text, "Second");
+        assert_eq!(
+            repairs,
+            [
+                repaired(4, "missing blank line between subtitles"),
+                repaired(7, "missing blank line between subtitles"),
+            ]
+        );
+    }
+
+    #[test]
+    fn negative_times_stay_negative() {
+        let (cues, repairs) = parse(
+            "1\n-00:00:01,500 --> 00:00:02,000\nBefore the start\n\n\
+             2\n-00:00:04,000 --> -00:00:03,000\nAll of it\n",
+        );
+        assert_eq!(times(&cues), [(-1500, 2000), (-4000, -3000)]);
+        assert_eq!(repairs, []);
+    }
+
+    #[test]
+    fn cues_without_text_are_reported() {
+        let (cues, repairs) = parse(
+            "1\n00:00:01,000 --> 00:00:02,000\n\n\
+             2\n00:00:03,000 --> 00:00:04,000\nText\n",
+        );
+        assert_eq!(times(&cues), [(3000, 4000)]);
+        assert_eq!(repairs, [skipped(2, "subtitle without text")]);
+    }
+}
@@ -0,0 +1,692 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use iced::{
+    Alignment, Color, Element, Font, Length, Padding, Point, Size, Theme,
+    alignment::{Horizontal, Vertical},
+    font::{Family, Style, Weight},
+    widget::{Column, Stack, container, image, span, stack, text::Rich},
+};
+
+use crate::{Message, ass::AssStyle};
+
+pub struct Subtitle {
+    /// Before the start of the playback when negative, after a resync.
+    pub start_time_ms: i128,
+    pub end_time_ms: i128,
+    pub runs: Vec<StyledRun>,
+    pub font: Font,
+    /// Resolved ASS style, `None` for formats without styles.
+    pub style: Option<AssStyle>,
+    /// Size of this subtitle relative to the user selected font size.
+    pub scale: f32,
+    /// Where on screen the subtitle goes, `None` keeps it in the middle.
+    pub placement: Option<Placement>,
+    pub fade: Option<Fade>,
+    /// Picture shown instead of the runs, for disc subtitles.
+    pub bitmap: Option<Bitmap>,
+    /// Colour of runs without their own, `None` uses the theme's text colour.
+    pub color: Option<Color>,
+}
+
+impl Subtitle {
+    pub fn new(start_t: i128, end_t: i128) -> Self {
+        Subtitle {
+            start_time_ms: start_t,
+            end_time_ms: end_t,
+            runs: Vec::new(),
+            font: Font::default(),
+            style: None,
+            scale: 1.0,
+            placement: None,
+            fade: None,
+            bitmap: None,
+            color: None,
+        }
+    }
+
+    /// A bitmap subtitle with its top left corner at `corner` on a video
+    /// of `video_size`. Without a video size the picture fills the area.
+    pub fn bitmap(
+        start_t: i128,
+        end_t: i128,
+        bitmap: Bitmap,
+        corner: Point,
+        video_size: Size,
+    ) -> Self {
+        let video_size = if video_size.width > 0.0 && video_size.height > 0.0 {
+            video_size
+        } else {
+            Size::new(
+                corner.x + bitmap.width.max(1) as f32,
+                corner.y + bitmap.height.max(1) as f32,
+            )
+        };
+        Subtitle {
+            bitmap: Some(bitmap),
+            placement: Some(Placement {
+                alignment: 7,
+                position: Some(Position::Fixed(corner)),
+                margin_l: 0.0,
+                margin_r: 0.0,
+                margin_v: 0.0,
+                resolution: video_size,
+            }),
+            ..Subtitle::new(start_t, end_t)
+        }
+    }
+
+    /// Stretches every time of the subtitle by `factor` and moves it by
+    /// `shift_ms`, for a video at another frame rate than the subtitles were
+    /// timed for or one they drift away from.
+    pub fn retime(&mut self, factor: f64, shift_ms: i128) {
+        let scale = |ms: u128| (ms as f64 * factor).round() as u128;
+        let move_to = |ms: i128| (ms as f64 * factor).round() as i128 + shift_ms;
+        self.start_time_ms = move_to(self.start_time_ms);
+        self.end_time_ms = move_to(self.end_time_ms);
+        self.fade = self.fade.map(|fade| match fade {
+            Fade::Simple {
+                fade_in_ms,
+                fade_out_ms,
+            } => Fade::Simple {
+                fade_in_ms: scale(fade_in_ms),
+                fade_out_ms: scale(fade_out_ms),
+            },
+            Fade::Complex { alphas, times } => Fade::Complex {
+                alphas,
+                times: times.map(scale),
+            },
+        });
+        if let Some(Placement {
+            position: Some(Position::Moving {
+                start_ms, end_ms, ..
+            }),
+            ..
+        }) = &mut self.placement
+        {
+            *start_ms = scale(*start_ms);
+            *end_ms = scale(*end_ms);
+        }
+        for karaoke in self.runs.iter_mut().filter_map(|run| run.karaoke.as_mut()) {
+            karaoke.start_ms = scale(karaoke.start_ms);
+            karaoke.duration_ms = scale(karaoke.duration_ms);
+        }
+    }
+
+    /// How long the subtitle has been shown at `playback_time`.
+    fn elapsed_ms(&self, playback_time: i128) -> u128 {
+        (playback_time - self.start_time_ms).max(0) as u128
+    }
+
+    fn duration_ms(&self) -> u128 {
+        (self.end_time_ms - self.start_time_ms).max(0) as u128
+    }
+
+    /// The text without styling, for showing which subtitle is meant.
+    pub fn plain_text(&self) -> String {
+        self.runs.iter().map(|run| run.text.as_str()).collect()
+    }
+
+    pub fn view<'a>(
+        &self,
+        font_size: u16,
+        playback_time: i128,
+        area: Size,
+    ) -> Element<'a, Message> {
+        let elapsed = self.elapsed_ms(playback_time);
+        let paint = Paint {
+            size: f32::from(font_size) * self.scale,
+            font: self.font,
+            color_override: None,
+            elapsed,
+            opacity: self
+                .fade
+                .map_or(1.0, |fade| fade.opacity(elapsed, self.duration_ms())),
+        };
+        if let Some(bitmap) = &self.bitmap {
+            // Bitmaps are drawn for the video size, scale them with the window
+            let scale = self.placement.map_or(1.0, |placement| {
+                (area.width / placement.resolution.width)
+                    .min(area.height / placement.resolution.height)
+            });
+            return image(bitmap.handle.clone())
+                .width(bitmap.width as f32 * scale)
+                .height(bitmap.height as f32 * scale)
+                .opacity(paint.opacity)
+                .into();
+        }
+        let Some(style) = &self.style else {
+            return self.rich_text(paint).into();
+        };
+
+        // Outline and box sizes are in script pixels, like the font size
+        let outline = (style.outline * paint.size / style.font_size).ceil();
+        let outline_color = style.outline_color.scale_alpha(paint.opacity);
+        if style.border_style == 3 {
+            return container(self.rich_text(paint))
+                .padding(outline.max(2.0))
+                .style(move |_| container::Style::default().background(outline_color))
+                .into();
+        }
+        if outline <= 0.0 {
+            return self.rich_text(paint).into();
+        }
+
+        // No text outlines in iced, so draw shifted copies underneath
+        let shifted = |dx: f32, dy: f32| {
+            container(self.rich_text(Paint {
+                color_override: Some(style.outline_color),
+                ..paint
+            }))
+            .padding(Padding {
+                top: outline + dy,
+                right: outline - dx,
+                bottom: outline - dy,
+                left: outline + dx,
+            })
+        };
+        stack![
+            shifted(-outline, -outline),
+            shifted(outline, -outline),
+            shifted(-outline, outline),
+            shifted(outline, outline),
+            container(self.rich_text(paint)).padding(outline),
+        ]
+        .into()
+    }
+
+    fn rich_text<'a>(&self, paint: Paint) -> Rich<'a, Message, Theme, iced::Renderer> {
+        let color = self.color;
+        self.runs
+            .iter()
+            .flat_map(|run| run.view(paint))
+            .collect::<Rich<'a, Message, Theme, iced::Renderer>>()
+            .size(paint.size)
+            .font(self.font)
+            .align_x(
+                self.placement
+                    .map_or(Horizontal::Left, |placement| placement.alignments().0),
+            )
+            // Runs without their own colour still have to fade
+            .style(move |theme: &Theme| iced::widget::text::Style {
+                color: Some(
+                    color
+                        .unwrap_or(theme.palette().text)
+                        .scale_alpha(paint.opacity),
+                ),
+            })
+    }
+}
+
+/// An RGBA picture, decoded once when the file is loaded.
+#[derive(Debug, Clone)]
+pub struct Bitmap {
+    handle: image::Handle,
+    width: u32,
+    height: u32,
+}
+
+impl Bitmap {
+    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Self {
+        Bitmap {
+            handle: image::Handle::from_rgba(width, height, rgba),
+            width,
+            height,
+        }
+    }
+}
+
+/// Everything a run needs to know to draw itself.
+#[derive(Debug, Clone, Copy)]
+struct Paint {
+    size: f32,
+    font: Font,
+    /// Replaces every colour, used to draw outlines.
+    color_override: Option<Color>,
+    /// Time since the subtitle appeared.
+    elapsed: u128,
+    opacity: f32,
+}
+
+/// How a subtitle fades in and out, relative to its start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fade {
+    /// `\fad(in,out)`, or the default fade for subtitles without one.
+    Simple { fade_in_ms: u128, fade_out_ms: u128 },
+    /// `\fade(a1,a2,a3,t1,t2,t3,t4)`: goes from `alphas[0]` to `alphas[1]`
+    /// between `times[0]` and `times[1]`, then to `alphas[2]` between
+    /// `times[2]` and `times[3]`. Alpha 0 is opaque and 255 invisible.
+    Complex { alphas: [u8; 3], times: [u128; 4] },
+}
+
+impl Fade {
+    fn opacity(&self, elapsed: u128, duration: u128) -> f32 {
+        // How far `elapsed` is between `start` and `end`, from 0 to 1
+        let progress = |start: u128, end: u128| {
+            if elapsed <= start {
+                0.0
+            } else if elapsed >= end {
+                1.0
+            } else {
+                (elapsed - start) as f32 / (end - start) as f32
+            }
+        };
+
+        match *self {
+            Fade::Simple {
+                fade_in_ms,
+                fade_out_ms,
+            } => {
+                let fade_in = progress(0, fade_in_ms);
+                let fade_out = 1.0 - progress(duration.saturating_sub(fade_out_ms), duration);
+                fade_in.min(fade_out)
+            }
+            Fade::Complex { alphas, times } => {
+                let [a1, a2, a3] = alphas.map(f32::from);
+                let alpha = if elapsed < times[2] {
+                    a1 + (a2 - a1) * progress(times[0], times[1])
+                } else {
+                    a2 + (a3 - a2) * progress(times[2], times[3])
+                };
+                1.0 - alpha / 255.0
+            }
+        }
+    }
+}
+
+/// Numpad style placement inside a coordinate space of `resolution`,
+/// like ASS uses with its `PlayResX`/`PlayResY`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    /// 1 is bottom left, 5 is the middle and 9 is top right.
+    pub alignment: u8,
+    /// Point the alignment is anchored to, margins are ignored when set.
+    pub position: Option<Position>,
+    pub margin_l: f32,
+    pub margin_r: f32,
+    pub margin_v: f32,
+    pub resolution: Size,
+}
+
+impl Placement {
+    fn alignments(&self) -> (Horizontal, Vertical) {
+        let alignment = self.alignment.clamp(1, 9) - 1;
+        let horizontal = match alignment % 3 {
+            0 => Horizontal::Left,
+            1 => Horizontal::Center,
+            _ => Horizontal::Right,
+        };
+        let vertical = match alignment / 3 {
+            0 => Vertical::Bottom,
+            1 => Vertical::Center,
+            _ => Vertical::Top,
+        };
+        (horizontal, vertical)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    Fixed(Point),
+    /// Moves between `start_ms` and `end_ms` after the subtitle appears,
+    /// both being zero means during the whole subtitle.
+    Moving {
+        from: Point,
+        to: Point,
+        start_ms: u128,
+        end_ms: u128,
+    },
+}
+
+impl Position {
+    fn at(&self, elapsed_ms: u128, duration_ms: u128) -> Point {
+        match *self {
+            Position::Fixed(point) => point,
+            Position::Moving {
+                from,
+                to,
+                start_ms,
+                end_ms,
+            } => {
+                let (start_ms, end_ms) = if start_ms == 0 && end_ms == 0 {
+                    (0, duration_ms)
+                } else {
+                    (start_ms, end_ms)
+                };
+                let progress = if elapsed_ms <= start_ms {
+                    0.0
+                } else if elapsed_ms >= end_ms {
+                    1.0
+                } else {
+                    (elapsed_ms - start_ms) as f32 / (end_ms - start_ms) as f32
+                };
+                Point::new(
+                    from.x + (to.x - from.x) * progress,
+                    from.y + (to.y - from.y) * progress,
+                )
+            }
+        }
+    }
+}
+
+/// Lays out the subtitles being shown inside an area of `area`. Subtitles
+/// without a placement are stacked horizontally in the middle and at
+/// `unplaced_y`, the others are grouped by alignment or put at their own position.
+pub fn view_placed<'a>(
+    subtitles: &[&Subtitle],
+    area: Size,
+    font_size: u16,
+    playback_time: i128,
+    unplaced_y: Vertical,
+) -> Element<'a, Message> {
+    let column = |alignment: Alignment| Column::new().spacing(10).align_x(alignment);
+
+    let unplaced = subtitles
+        .iter()
+        .filter(|subtitle| subtitle.placement.is_none())
+        .fold(column(Alignment::Center), |accu, sub| {
+            accu.push(sub.view(font_size, playback_time, area))
+        });
+    let mut layers = Stack::new().width(Length::Fill).height(Length::Fill).push(
+        container(unplaced)
+            .center_x(Length::Fill)
+            .height(Length::Fill)
+            .align_y(unplaced_y),
+    );
+
+    for alignment in 1..=9 {
+        let group = subtitles
+            .iter()
+            .filter(|subtitle| {
+                subtitle.placement.is_some_and(|placement| {
+                    placement.position.is_none() && placement.alignment == alignment
+                })
+            })
+            .collect::<Vec<_>>();
+        let Some(placement) = group.first().and_then(|subtitle| subtitle.placement) else {
+            continue;
+        };
+
+        let (horizontal, vertical) = placement.alignments();
+        let scale_x = area.width / placement.resolution.width;
+        let scale_y = area.height / placement.resolution.height;
+        let padding = Padding {
+            top: if vertical == Vertical::Top {
+                placement.margin_v * scale_y
+            } else {
+                0.0
+            },
+            right: if horizontal != Horizontal::Left {
+                placement.margin_r * scale_x
+            } else {
+                0.0
+            },
+            bottom: if vertical == Vertical::Bottom {
+                placement.margin_v * scale_y
+            } else {
+                0.0
+            },
+            left: if horizontal != Horizontal::Right {
+                placement.margin_l * scale_x
+            } else {
+                0.0
+            },
+        };
+
+        let content = group.iter().fold(column(horizontal.into()), |accu, sub| {
+            accu.push(sub.view(font_size, playback_time, area))
+        });
+        layers = layers.push(
+            container(content)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .align_x(horizontal)
+                .align_y(vertical)
+                .padding(padding),
+        );
+    }
+
+    for subtitle in subtitles {
+        let Some(placement) = subtitle.placement else {
+            continue;
+        };
+        let Some(position) = placement.position else {
+            continue;
+        };
+
+        let point = position.at(subtitle.elapsed_ms(playback_time), subtitle.duration_ms());
+        let anchor = Point::new(
+            point.x * area.width / placement.resolution.width,
+            point.y * area.height / placement.resolution.height,
+        );
+        let (horizontal, vertical) = placement.alignments();
+        let (left, right) = match horizontal {
+            Horizontal::Left => (anchor.x, 0.0),
+            Horizontal::Center => centered_on(anchor.x, area.width),
+            Horizontal::Right => (0.0, area.width - anchor.x),
+        };
+        let (top, bottom) = match vertical {
+            Vertical::Top => (anchor.y, 0.0),
+            Vertical::Center => centered_on(anchor.y, area.height),
+            Vertical::Bottom => (0.0, area.height - anchor.y),
+        };
+
+        layers = layers.push(
+            container(subtitle.view(font_size, playback_time, area))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .align_x(horizontal)
+                .align_y(vertical)
+                .padding(Padding {
+                    top: top.max(0.0),
+                    right: right.max(0.0),
+                    bottom: bottom.max(0.0),
+                    left: left.max(0.0),
+                }),
+        );
+    }
+
+    layers.into()
+}
+
+/// Padding before and after so the middle of what is left lands on `at`.
+fn centered_on(at: f32, length: f32) -> (f32, f32) {
+    if at * 2.0 <= length {
+        (0.0, length - at * 2.0)
+    } else {
+        (at * 2.0 - length, 0.0)
+    }
+}
+
+/// Styling that can change in the middle of a subtitle line.
+/// `None` means "use whatever the subtitle itself uses".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub color: Option<Color>,
+    /// Karaoke colour of syllables that have been sung.
+    pub secondary_color: Option<Color>,
+    /// Multiplier on top of the user selected font size.
+    pub size: Option<f32>,
+    /// Font family requested by the subtitle file, see [`font_family`].
+    pub family: Option<&'static str>,
+}
+
+/// A piece of subtitle text sharing the same style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledRun {
+    pub text: String,
+    pub style: RunStyle,
+    /// Karaoke syllable timing, if the text is part of a song.
+    pub karaoke: Option<Karaoke>,
+}
+
+/// Timing of a karaoke syllable, relative to the subtitle start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Karaoke {
+    pub start_ms: u128,
+    pub duration_ms: u128,
+    pub kind: KaraokeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KaraokeKind {
+    /// `\k`, the whole syllable changes colour when it starts.
+    Instant,
+    /// `\kf`/`\K`, the colour sweeps over the syllable while it is sung.
+    Sweep,
+    /// `\ko`, only the outline changes in ASS. Without outline
+    /// highlighting this behaves like [`KaraokeKind::Instant`].
+    Outline,
+}
+
+impl StyledRun {
+    pub fn plain<T: Into<String>>(text: T) -> Self {
+        StyledRun {
+            text: text.into(),
+            style: RunStyle::default(),
+            karaoke: None,
+        }
+    }
+
+    /// Spans for this run, karaoke syllables are split into the sung
+    /// part in the secondary colour and the rest in the primary colour.
+    fn view<'a>(&self, paint: Paint) -> Vec<iced::widget::text::Span<'a, Message>> {
+        let Some(karaoke) = self.karaoke else {
+            return vec![self.span(self.text.clone(), paint, paint.color_override)];
+        };
+
+        let elapsed = paint.elapsed;
+        let sung_color = paint.color_override.or(self.style.secondary_color);
+        let sung_chars = match karaoke.kind {
+            _ if elapsed < karaoke.start_ms => 0,
+            KaraokeKind::Instant | KaraokeKind::Outline => self.text.chars().count(),
+            KaraokeKind::Sweep => {
+                let progress = if karaoke.duration_ms == 0 {
+                    1.0
+                } else {
+                    ((elapsed - karaoke.start_ms) as f32 / karaoke.duration_ms as f32).min(1.0)
+                };
+                (self.text.chars().count() as f32 * progress).round() as usize
+            }
+        };
+
+        let split = self
+            .text
+            .char_indices()
+            .nth(sung_chars)
+            .map_or(self.text.len(), |(index, _)| index);
+        let (sung, unsung) = self.text.split_at(split);
+        [(sung, sung_color), (unsung, paint.color_override)]
+            .into_iter()
+            .filter(|(text, _)| !text.is_empty())
+            .map(|(text, color)| self.span(text.to_string(), paint, color))
+            .collect()
+    }
+
+    fn span<'a>(
+        &self,
+        text: String,
+        paint: Paint,
+        color_override: Option<Color>,
+    ) -> iced::widget::text::Span<'a, Message> {
+        let base_font = paint.font;
+        let font = Font {
+            weight: if self.style.bold {
+                Weight::Bold
+            } else {
+                base_font.weight
+            },
+            style: if self.style.italic {
+                Style::Italic
+            } else {
+                base_font.style
+            },
+            family: self.style.family.map_or(base_font.family, Family::Name),
+            ..base_font
+        };
+
+        span(text)
+            .font(font)
+            .size(paint.size * self.style.size.unwrap_or(1.0))
+            .color_maybe(
+                color_override
+                    .or(self.style.color)
+                    .map(|color| color.scale_alpha(paint.opacity)),
+            )
+            .underline(self.style.underline)
+            .strikethrough(self.style.strikethrough)
+    }
+}
+
+/// Fonts in iced need a `&'static str` name, so every distinct name coming
+/// from a subtitle file is leaked once and reused afterwards.
+pub fn font_family(name: &str) -> &'static str {
+    static NAMES: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+
+    let mut names = NAMES.lock().unwrap();
+    let names = names.get_or_insert_with(HashSet::new);
+    if let Some(known) = names.get(name) {
+        return known;
+    }
+    let leaked: &'static str = Box::leak(name.to_string().into_boxed_str());
+    names.insert(leaked);
+    leaked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retiming_can_move_before_the_start() {
+        let mut subtitle = Subtitle::new(1000, 3000);
+        subtitle.retime(2.0, -4000);
+        assert_eq!(
+            (subtitle.start_time_ms, subtitle.end_time_ms),
+            (-2000, 2000)
+        );
+        assert_eq!(subtitle.elapsed_ms(-2500), 0);
+        assert_eq!(subtitle.elapsed_ms(500), 2500);
+        assert_eq!(subtitle.duration_ms(), 4000);
+    }
+
+    /// Subtitles timed for a 23.976 fps video shown with a 25 fps one.
+    const FILM_TO_PAL: f64 = (24000.0 / 1001.0) / 25.0;
+
+    #[test]
+    fn frame_rate_conversion() {
+        let mut subtitle = Subtitle::new(60_000, 62_000);
+        subtitle.retime(FILM_TO_PAL, 0);
+        assert_eq!(
+            (subtitle.start_time_ms, subtitle.end_time_ms),
+            (57_542, 59_461)
+        );
+    }
+
+    #[test]
+    fn converting_back_restores_the_times() {
+        let times = [(0, 1500), (60_000, 62_000), (5_399_123, 5_401_999)];
+        for (start, end) in times {
+            let mut subtitle = Subtitle {
+                fade: Some(Fade::Simple {
+                    fade_in_ms: 200,
+                    fade_out_ms: 300,
+                }),
+                ..Subtitle::new(start, end)
+            };
+            subtitle.retime(FILM_TO_PAL, 0);
+            subtitle.retime(1.0 / FILM_TO_PAL, 0);
+            assert_eq!((subtitle.start_time_ms, subtitle.end_time_ms), (start, end));
+            assert_eq!(
+                subtitle.fade,
+                Some(Fade::Simple {
+                    fade_in_ms: 200,
+                    fade_out_ms: 300,
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{html, subtitle::Subtitle};
+
+/// Checks whether a `.sub` file is SubViewer instead of MicroDVD, which
+/// uses the same extension.
+pub fn is_subviewer(data: &str) -> bool {
+    data.lines()
+        .map(|line| line.trim().trim_start_matches('\u{feff}'))
+        .filter(|line| !line.is_empty())
+        .take(20)
+        .any(|line| line.eq_ignore_ascii_case("[INFORMATION]") || parse_timing(line).is_some())
+}
+
+/// Parses a SubViewer 2.0 file. Every cue is a `00:00:41.00,00:00:44.40`
+/// timing line followed by text, with `[br]` as the line break.
+pub fn parse(data: &str) -> Vec<Subtitle> {
+    let mut subtitles = Vec::new();
+    let mut lines = data.lines().map(|line| line.trim_start_matches('\u{feff}'));
+
+    while let Some(line) = lines.next() {
+        let Some((start_time_ms, end_time_ms)) = parse_timing(line.trim()) else {
+            continue;
+        };
+
+        let text = lines
+            .by_ref()
+            .map(str::trim_end)
+            .take_while(|line| !line.is_empty())
+            .collect::<Vec<&str>>()
+            .join("\n")
+            .replace("[br]", "\n")
+            .replace("[BR]", "\n");
+
+        subtitles.push(Subtitle {
+            runs: html::parse_markup(&text),
+            ..Subtitle::new(start_time_ms, end_time_ms)
+        });
+    }
+
+    subtitles
+}
+
+fn parse_timing(line: &str) -> Option<(i128, i128)> {
+    let (start, end) = line.split_once(',')?;
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+/// `hh:mm:ss.cc`, the fraction is in hundredths of a second.
+fn parse_timestamp(input: &str) -> Option<i128> {
+    let (clock, fraction) = input.trim().split_once('.')?;
+    let parts = clock
+        .split(':')
+        .map(|part| part.parse::<i128>().ok())
+        .collect::<Option<Vec<i128>>>()?;
+    let [hh, mm, ss] = parts[..] else {
+        return None;
+    };
+    if mm >= 60 || ss >= 60 || fraction.is_empty() || fraction.len() > 3 {
+        return None;
+    }
+    // "5" is half a second and "50" as well, so pad to milliseconds
+    let ms = format!("{fraction:0<3}").parse::<i128>().ok()?;
+
+    Some(hh * 3600000 + mm * 60000 + ss * 1000 + ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timings_and_line_breaks() {
+        let data = "[INFORMATION]\n[TITLE]Film\n[END INFORMATION]\n\n\
+                    00:00:41.00,00:00:44.40\nFirst line[br]second line\n\n\
+                    01:02:03.5,01:02:04.125\nShort and long fractions\n";
+        assert!(is_subviewer(data));
+        let subtitles = parse(data);
+        let times = subtitles
+            .iter()
+            .map(|subtitle| (subtitle.start_time_ms, subtitle.end_time_ms))
+            .collect::<Vec<(i128, i128)>>();
+        assert_eq!(times, [(41_000, 44_400), (3_723_500, 3_724_125)]);
+        assert_eq!(subtitles[0].plain_text(), "First line\nsecond line");
+    }
+
+    #[test]
+    fn micro_dvd_is_not_subviewer() {
+        assert!(!is_subviewer("{1}{1}23.976\n{24}{48}Hello|world\n"));
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+
+use iced::{Color, Size};
+use roxmltree::{Document, Node};
+
+use crate::{
+    html,
+    subtitle::{self, Placement, RunStyle, StyledRun, Subtitle},
+};
+
+/// Attributes by their name without namespace, TTML, DFXP and SMPTE-TT
+/// all use different namespaces for the same styling attributes.
+type Attributes<'a> = HashMap<&'a str, &'a str>;
+
+/// Checks whether an XML file is a TTML document.
+pub fn is_ttml(data: &str) -> bool {
+    let data = data.trim_start_matches('\u{feff}').trim_start();
+    (data.starts_with("<?xml") || data.starts_with("<tt")) && data.contains("<tt")
+}
+
+/// How frame and tick based times are turned into milliseconds.
+struct Timebase {
+    frame_rate: f64,
+    tick_rate: f64,
+}
+
+/// Everything inherited from the elements around a paragraph.
+#[derive(Clone)]
+struct Context<'a> {
+    begin_ms: f64,
+    end_ms: Option<f64>,
+    attributes: Attributes<'a>,
+    region: Option<&'a str>,
+}
+
+struct Ttml<'a, 'input> {
+    timebase: Timebase,
+    /// Root `tts:extent`, needed for regions positioned in pixels.
+    extent: Option<Size>,
+    styles: HashMap<&'a str, Node<'a, 'input>>,
+    regions: HashMap<&'a str, Node<'a, 'input>>,
+}
+
+/// Parses a TTML (DFXP, SMPTE-TT) document. Regions are turned into a
+/// [`Placement`] in a 100 by 100 space.
+pub fn parse(data: &str) -> Result<Vec<Subtitle>, roxmltree::Error> {
+    let xml = Document::parse(data.trim_start_matches('\u{feff}'))?;
+    let root = xml.root_element();
+
+    let frame_rate = attribute(root, "frameRate")
+        .and_then(|rate| rate.trim().parse::<f64>().ok())
+        .unwrap_or(30.0);
+    let multiplier = attribute(root, "frameRateMultiplier")
+        .and_then(|multiplier| {
+            let (numerator, denominator) = multiplier.split_once(' ')?;
+            Some(numerator.trim().parse::<f64>().ok()? / denominator.trim().parse::<f64>().ok()?)
+        })
+        .unwrap_or(1.0);
+    let frame_rate = frame_rate * multiplier;
+    let tick_rate = attribute(root, "tickRate")
+        .and_then(|rate| rate.trim().parse::<f64>().ok())
+        .unwrap_or(if attribute(root, "frameRate").is_some() {
+            frame_rate
+        } else {
+            1.0
+        });
+
+    let mut document = Ttml {
+        timebase: Timebase {
+            frame_rate,
+            tick_rate,
+        },
+        extent: attribute(root, "extent").and_then(|extent| {
+            let (width, height) = extent.split_once(' ')?;
+            let pixels = |value: &str| value.trim().strip_suffix("px")?.parse::<f32>().ok();
+            Some(Size::new(pixels(width)?, pixels(height)?))
+        }),
+        styles: HashMap::new(),
+        regions: HashMap::new(),
+    };
+    for node in root.descendants().filter(Node::is_element) {
+        let Some(id) = attribute(node, "id") else {
+            continue;
+        };
+        match node.tag_name().name() {
+            "style" => document.styles.insert(id, node),
+            "region" => document.regions.insert(id, node),
+            _ => None,
+        };
+    }
+
+    let mut subtitles = Vec::new();
+    if let Some(body) = root.children().find(|node| node.has_tag_name("body")) {
+        let context = Context {
+            begin_ms: 0.0,
+            end_ms: None,
+            attributes: Attributes::new(),
+            region: None,
+        };
+        walk(body, &context, &document, &mut subtitles);
+    }
+    subtitles.sort_by_key(|subtitle| subtitle.start_time_ms);
+
+    Ok(subtitles)
+}
+
+/// Walks `body` and `div` elements down to the paragraphs, which are the cues.
+fn walk<'a>(
+    node: Node<'a, '_>,
+    parent: &Context<'a>,
+    document: &Ttml<'a, '_>,
+    subtitles: &mut Vec<Subtitle>,
+) {
+    let mut context = parent.clone();
+    if let Some(begin) = attribute(node, "begin").and_then(|time| parse_time(time, document)) {
+        context.begin_ms = parent.begin_ms + begin;
+    }
+    if let Some(end) = attribute(node, "end").and_then(|time| parse_time(time, document)) {
+        context.end_ms = Some(parent.begin_ms + end);
+    }
+    if let Some(duration) = attribute(node, "dur").and_then(|time| parse_time(time, document)) {
+        context.end_ms = Some(context.begin_ms + duration);
+    }
+    context.region = attribute(node, "region").or(parent.region);
+    context.attributes.extend(own_attributes(node, document));
+
+    if node.tag_name().name() != "p" {
+        for child in node.children().filter(Node::is_element) {
+            walk(child, &context, document, subtitles);
+        }
+        return;
+    }
+
+    let Some(end_ms) = context.end_ms else {
+        return;
+    };
+    let region = context
+        .region
+        .and_then(|id| document.regions.get(id))
+        .map(|region| own_attributes(*region, document));
+
+    // Region styling applies to the paragraph unless it sets its own
+    let mut attributes = region.clone().unwrap_or_default();
+    attributes.extend(context.attributes.iter());
+
+    let mut runs = Vec::new();
+    collect_runs(node, &attributes, document, &mut runs);
+    trim_runs(&mut runs);
+    if runs.is_empty() {
+        return;
+    }
+
+    subtitles.push(Subtitle {
+        runs,
+        placement: region.and_then(|region| placement(&region, &attributes, document.extent)),
+        ..Subtitle::new(context.begin_ms.round() as i128, end_ms.round() as i128)
+    });
+}
+
+/// Styling attributes set on `node`, through referenced styles, nested
+/// `<style>` elements and its own attributes, in that order.
+fn own_attributes<'a>(node: Node<'a, '_>, document: &Ttml<'a, '_>) -> Attributes<'a> {
+    let mut attributes = Attributes::new();
+    add_referenced_styles(node, document, &mut attributes, 0);
+    for style in node.children().filter(|child| child.has_tag_name("style")) {
+        add_referenced_styles(style, document, &mut attributes, 0);
+        attributes.extend(style.attributes().map(|attr| (attr.name(), attr.value())));
+    }
+    attributes.extend(node.attributes().map(|attr| (attr.name(), attr.value())));
+    attributes
+}
+
+/// Styles can reference other styles, `depth` stops reference loops.
+fn add_referenced_styles<'a>(
+    node: Node<'a, '_>,
+    document: &Ttml<'a, '_>,
+    attributes: &mut Attributes<'a>,
+    depth: u8,
+) {
+    if depth > 8 {
+        return;
+    }
+    let Some(references) = attribute(node, "style") else {
+        return;
+    };
+    for id in references.split_whitespace() {
+        if let Some(style) = document.styles.get(id) {
+            add_referenced_styles(*style, document, attributes, depth + 1);
+            attributes.extend(style.attributes().map(|attr| (attr.name(), attr.value())));
+        }
+    }
+}
+
+/// Turns the text, `<span>` and `<br/>` content of a paragraph into runs,
+/// collapsing white space like XML does.
+fn collect_runs<'a>(
+    node: Node<'a, '_>,
+    attributes: &Attributes<'a>,
+    document: &Ttml<'a, '_>,
+    runs: &mut Vec<StyledRun>,
+) {
+    let style = run_style(attributes);
+    for child in node.children() {
+        if child.is_text() {
+            let mut text = String::new();
+            let mut after_space = runs
+                .last()
+                .is_none_or(|run| run.text.ends_with([' ', '\n']));
+            for c in child.text().unwrap_or_default().chars() {
+                if c.is_whitespace() {
+                    if !after_space {
+                        text.push(' ');
+                    }
+                    after_space = true;
+                } else {
+                    text.push(c);
+                    after_space = false;
+                }
+            }
+            push_run(runs, text, &style);
+        } else if child.has_tag_name("br") {
+            if let Some(last) = runs.last_mut() {
+                last.text.truncate(last.text.trim_end_matches(' ').len());
+            }
+            push_run(runs, String::from("\n"), &style);
+        } else if child.has_tag_name("span") {
+            let mut span_attributes = attributes.clone();
+            span_attributes.extend(own_attributes(child, document));
+            collect_runs(child, &span_attributes, document, runs);
+        }
+    }
+}
+
+fn push_run(runs: &mut Vec<StyledRun>, text: String, style: &RunStyle) {
+    if text.is_empty() {
+        return;
+    }
+    match runs.last_mut() {
+        Some(last) if last.style == *style => last.text.push_str(&text),
+        _ => runs.push(StyledRun {
+            text,
+            style: style.clone(),
+            karaoke: None,
+        }),
+    }
+}
+
+/// Drops the space collapsing leaves at the end of the paragraph.
+fn trim_runs(runs: &mut Vec<StyledRun>) {
+    while let Some(last) = runs.last_mut() {
+        last.text.truncate(last.text.trim_end().len());
+        if !last.text.is_empty() {
+            break;
+        }
+        runs.pop();
+    }
+}
+
+fn run_style(attributes: &Attributes) -> RunStyle {
+    let mut style = RunStyle::default();
+    for (&name, &value) in attributes {
+        let value = value.trim();
+        match name {
+            "color" => style.color = parse_color(value),
+            "fontStyle" => style.italic = value == "italic" || value == "oblique",
+            "fontWeight" => style.bold = value == "bold",
+            "textDecoration" => {
+                style.underline = value.contains("underline") && !value.contains("noUnderline");
+                style.strikethrough =
+                    value.contains("lineThrough") && !value.contains("noLineThrough");
+            }
+            "fontFamily" => {
+                let family = value.split(',').next().unwrap_or_default();
+                let family = family.trim().trim_matches(['"', '\'']);
+                // Generic families like "proportionalSansSerif" are left to the user font
+                if !family.is_empty() && !family.contains("Serif") && family != "default" {
+                    style.family = Some(subtitle::font_family(family));
+                }
+            }
+            "fontSize" => {
+                let size = value.split_whitespace().last().unwrap_or_default();
+                style.size = if let Some(percent) = size.strip_suffix('%') {
+                    percent.parse::<f32>().ok().map(|percent| percent / 100.0)
+                } else {
+                    size.strip_suffix("em")
+                        .and_then(|em| em.parse::<f32>().ok())
+                };
+            }
+            _ => {}
+        }
+    }
+    style
+}
+
+/// `#rrggbb`, `#rrggbbaa`, `rgb(r,g,b)`, `rgba(r,g,b,a)` or a named colour.
+fn parse_color(value: &str) -> Option<Color> {
+    if let Some(hex) = value.strip_prefix('#')
+        && hex.len() == 8
+    {
+        let rgba = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::from_rgba8(
+            (rgba >> 24) as u8,
+            (rgba >> 16) as u8,
+            (rgba >> 8) as u8,
+            (rgba & 0xff) as f32 / 255.0,
+        ));
+    }
+    if let Some(arguments) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+    {
+        let channels = arguments
+            .trim_end_matches(')')
+            .split(',')
+            .map(|channel| channel.trim().parse::<u8>().ok())
+            .collect::<Option<Vec<u8>>>()?;
+        return match channels[..] {
+            [r, g, b] => Some(Color::from_rgb8(r, g, b)),
+            [r, g, b, a] => Some(Color::from_rgba8(r, g, b, f32::from(a) / 255.0)),
+            _ => None,
+        };
+    }
+    html::parse_color(value)
+}
+
+/// Places a paragraph inside its region, the region's origin and extent
+/// become margins and `displayAlign`/`textAlign` the alignment.
+fn placement(
+    region: &Attributes,
+    attributes: &Attributes,
+    extent: Option<Size>,
+) -> Option<Placement> {
+    let (x, y) = parse_lengths(region.get("origin")?, extent)?;
+    let (width, height) = region
+        .get("extent")
+        .and_then(|value| parse_lengths(value, extent))
+        .unwrap_or((100.0 - x, 100.0 - y));
+
+    let row = match region.get("displayAlign").copied() {
+        Some("after") => 0,
+        Some("center") => 3,
+        _ => 6,
+    };
+    let column = match attributes.get("textAlign").copied() {
+        Some("center") => 2,
+        Some("right" | "end") => 3,
+        _ => 1,
+    };
+
+    Some(Placement {
+        alignment: row + column,
+        position: None,
+        margin_l: x,
+        margin_r: (100.0 - x - width).max(0.0),
+        margin_v: if row == 0 {
+            (100.0 - y - height).max(0.0)
+        } else {
+            y
+        },
+        resolution: Size::new(100.0, 100.0),
+    })
+}
+
+/// A pair of lengths like `10% 80%` or `192px 864px`, as percentages.
+/// Pixels need the root extent to be turned into percentages.
+fn parse_lengths(value: &str, extent: Option<Size>) -> Option<(f32, f32)> {
+    let mut lengths = value.split_whitespace();
+    let (first, second) = (lengths.next()?, lengths.next()?);
+
+    let length = |value: &str, full: Option<f32>| {
+        if let Some(percent) = value.strip_suffix('%') {
+            percent.parse::<f32>().ok()
+        } else {
+            Some(value.strip_suffix("px")?.parse::<f32>().ok()? / full? * 100.0)
+        }
+    };
+
+    Some((
+        length(first, extent.map(|extent| extent.width))?,
+        length(second, extent.map(|extent| extent.height))?,
+    ))
+}
+
+/// Clock times (`01:02:03.456`, `01:02:03:12` with frames) and offset
+/// times (`1.5s`, `20f`, `1000t`) in milliseconds.
+fn parse_time(value: &str, document: &Ttml) -> Option<f64> {
+    let value = value.trim();
+    let timebase = &document.timebase;
+
+    if value.contains(':') {
+        let parts = value.split(':').collect::<Vec<&str>>();
+        let (hours, minutes, seconds) = (
+            parts.first()?.parse::<f64>().ok()?,
+            parts.get(1)?.parse::<f64>().ok()?,
+            parts.get(2)?.parse::<f64>().ok()?,
+        );
+        let frames = match parts.get(3) {
+            Some(frames) => frames.parse::<f64>().ok()? / timebase.frame_rate,
+            None => 0.0,
+        };
+        return Some((hours * 3600.0 + minutes * 60.0 + seconds + frames) * 1000.0);
+    }
+
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, metric) = value.split_at(split);
+    let number = number.parse::<f64>().ok()?;
+    let seconds = match metric {
+        "h" => number * 3600.0,
+        "m" => number * 60.0,
+        "s" => number,
+        "ms" => number / 1000.0,
+        "f" => number / timebase.frame_rate,
+        "t" => number / timebase.tick_rate,
+        _ => return None,
+    };
+    Some(seconds * 1000.0)
+}
+
+/// Looks an attribute up by its name without namespace.
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes()
+        .find(|attr| attr.name() == name)
+        .map(|attr| attr.value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(document: &str) -> Vec<(i128, i128)> {
+        parse(document)
+            .unwrap()
+            .iter()
+            .map(|subtitle| (subtitle.start_time_ms, subtitle.end_time_ms))
+            .collect()
+    }
+
+    #[test]
+    fn tick_and_frame_times() {
+        let ticks = r#"<tt xmlns="http://www.w3.org/ns/ttml"
+            xmlns:ttp="http://www.w3.org/ns/ttml#parameter" ttp:tickRate="10000000">
+            <body><div>
+                <p begin="15000000t" end="32500000t">Ticks</p>
+            </div></body></tt>"#;
+        assert_eq!(times(ticks), [(1500, 3250)]);
+
+        let frames = r#"<tt xmlns="http://www.w3.org/ns/ttml"
+            xmlns:ttp="http://www.w3.org/ns/ttml#parameter"
+            ttp:frameRate="30" ttp:frameRateMultiplier="1000 1001">
+            <body><div>
+                <p begin="00:00:01:15" end="60f">Frames</p>
+                <p begin="30t" dur="2s">Ticks at the frame rate</p>
+            </div></body></tt>"#;
+        assert_eq!(times(frames), [(1001, 3001), (1501, 2002)]);
+    }
+
+    #[test]
+    fn nested_begin_is_an_offset() {
+        let document = r#"<tt xmlns="http://www.w3.org/ns/ttml"><body begin="10s">
+            <div begin="00:01:00.000">
+                <p begin="1s" end="2s">Inside both</p>
+                <p begin="500ms" dur="250ms">With a duration</p>
+            </div>
+            <div end="5s"><p begin="1s">Ends with the div</p></div>
+        </body></tt>"#;
+        assert_eq!(
+            times(document),
+            [
+                (10_000 + 1000, 10_000 + 5000),
+                (70_500, 70_750),
+                (71_000, 72_000)
+            ]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::{collections::HashMap, fmt};
+
+/// The subtitle tracks of a video file and their samples, read by
+/// [`crate::mkv`] or [`crate::mp4`].
+pub struct Video {
+    pub tracks: Vec<Track>,
+    pub blocks: HashMap<u64, Vec<Block>>,
+}
+
+impl Video {
+    pub fn track(&self, number: u64) -> Option<&Track> {
+        self.tracks.iter().find(|track| track.number == number)
+    }
+
+    pub fn blocks(&self, number: u64) -> &[Block] {
+        self.blocks.get(&number).map_or(&[], Vec::as_slice)
+    }
+
+    /// The track to show first, a default track that is not just forced
+    /// subtitles if there is one, out of the tracks the app can read.
+    pub fn first_track(&self) -> Option<u64> {
+        let readable = || self.tracks.iter().filter(|track| track.codec.is_text());
+        readable()
+            .find(|track| track.default && !track.forced)
+            .or_else(|| readable().find(|track| track.default))
+            .or_else(|| readable().next())
+            .map(|track| track.number)
+    }
+}
+
+/// What the blocks of a track hold.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Codec {
+    /// SRT text with its tags.
+    Srt,
+    /// Dialogue lines without times, the header is in the codec private data.
+    Ass,
+    Ssa,
+    /// Cue payloads with the settings as additional data, the header and
+    /// styles are in the codec private data.
+    WebVtt,
+    /// 3GPP timed text, the blocks are plain text.
+    TimedText,
+    /// Anything else, with the name the container gives it.
+    Other(String),
+}
+
+impl Codec {
+    /// Whether the track can be loaded.
+    pub fn is_text(&self) -> bool {
+        !matches!(self, Codec::Other(_))
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Codec::Srt => write!(f, "SRT"),
+            Codec::Ass => write!(f, "ASS"),
+            Codec::Ssa => write!(f, "SSA"),
+            Codec::WebVtt => write!(f, "WebVTT"),
+            Codec::TimedText => write!(f, "3GPP timed text"),
+            Codec::Other(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// A subtitle track as listed in the track picker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub number: u64,
+    pub codec: Codec,
+    pub codec_private: Vec<u8>,
+    pub language: String,
+    pub name: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+}
+
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {} ({})", self.number, self.codec, self.language)?;
+        if let Some(name) = &self.name {
+            write!(f, " {name}")?;
+        }
+        if self.default {
+            write!(f, " [default]")?;
+        }
+        if self.forced {
+            write!(f, " [forced]")?;
+        }
+        Ok(())
+    }
+}
+
+/// One subtitle of a track, the data depends on the [`Codec`].
+pub struct Block {
+    pub start_time_ms: i128,
+    pub end_time_ms: i128,
+    pub data: Vec<u8>,
+    /// WebVTT keeps the cue settings here.
+    pub additional: Option<Vec<u8>>,
+}
+
+/// Rebuilds an ASS/SSA script from the header in the codec private data
+/// and the blocks, which are dialogue lines without their times.
+pub fn ass_script(track: &Track, blocks: &[Block]) -> String {
+    let mut script = String::from_utf8_lossy(&track.codec_private).into_owned();
+    if !script.ends_with('\n') {
+        script.push('\n');
+    }
+    if !script.to_ascii_lowercase().contains("[events]") {
+        let first = if track.codec == Codec::Ssa {
+            "Marked"
+        } else {
+            "Layer"
+        };
+        script.push_str(&format!(
+            "\n[Events]\nFormat: {first}, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n"
+        ));
+    }
+
+    // ReadOrder, Layer, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+    let mut lines = blocks
+        .iter()
+        .filter_map(|block| {
+            let data = String::from_utf8_lossy(&block.data);
+            let mut fields = data.splitn(3, ',');
+            let read_order = fields.next()?.trim().parse::<u64>().unwrap_or(u64::MAX);
+            let layer = fields.next()?;
+            let rest = fields.next()?;
+            let line = format!(
+                "Dialogue: {layer},{},{},{rest}",
+                ass_time(block.start_time_ms),
+                ass_time(block.end_time_ms)
+            );
+            Some((read_order, line))
+        })
+        .collect::<Vec<(u64, String)>>();
+    // Back in the order of the original file
+    lines.sort_by_key(|(read_order, _)| *read_order);
+
+    for (_, line) in lines {
+        script.push_str(&line);
+        script.push('\n');
+    }
+    script
+}
+
+/// Rebuilds a WebVTT file from the header in the codec private data and
+/// the blocks, which are cue payloads with the settings kept aside.
+pub fn webvtt_text(track: &Track, blocks: &[Block]) -> String {
+    let mut text = String::from_utf8_lossy(&track.codec_private).into_owned();
+    if !text.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        text.insert_str(0, "WEBVTT\n\n");
+    }
+
+    for block in blocks {
+        // The settings are the first line of the additional data
+        let settings = block.additional.as_ref().map_or_else(String::new, |data| {
+            String::from_utf8_lossy(data)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        });
+        let payload = String::from_utf8_lossy(&block.data)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<&str>>()
+            .join("\n");
+        text.push_str(&format!(
+            "\n\n{} --> {} {settings}\n{payload}",
+            vtt_time(block.start_time_ms),
+            vtt_time(block.end_time_ms)
+        ));
+    }
+    text
+}
+
+/// `h:mm:ss.cc`, neither ASS nor WebVTT has times before the start.
+fn ass_time(ms: i128) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        ms / 3600000,
+        ms / 60000 % 60,
+        ms / 1000 % 60,
+        ms % 1000 / 10
+    )
+}
+
+/// `hh:mm:ss.mmm`
+fn vtt_time(ms: i128) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3600000,
+        ms / 60000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
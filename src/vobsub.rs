@@ -0,0 +1,394 @@
+use iced::{Point, Size};
+
+use crate::subtitle::{Bitmap, Subtitle};
+
+/// How long a subtitle stays when it has no stop command and is the last one.
+const LAST_SUBTITLE_MS: i128 = 5000;
+
+/// Checks for the MPEG program stream pack header a VobSub `.sub` starts with.
+pub fn is_vobsub(data: &[u8]) -> bool {
+    data.starts_with(&[0x00, 0x00, 0x01, 0xba])
+}
+
+/// The parts of an `.idx` file needed to decode the `.sub` next to it.
+struct Index {
+    video_size: Size,
+    palette: [[u8; 3]; 16],
+    /// Start time and position in the `.sub` of every subtitle.
+    entries: Vec<(i128, usize)>,
+}
+
+/// Parses a DVD subtitle track from its `.idx` text and `.sub` data. Only
+/// the first language in the index is used.
+pub fn parse(idx: &str, sub: &[u8]) -> Vec<Subtitle> {
+    let index = parse_index(idx);
+    let mut subtitles = Vec::new();
+
+    for (number, &(start_time_ms, filepos)) in index.entries.iter().enumerate() {
+        let Some(packet) = sub.get(filepos..).and_then(read_packet) else {
+            continue;
+        };
+        let Some(decoded) = decode_packet(&packet, &index.palette) else {
+            continue;
+        };
+
+        let next_start_ms = index
+            .entries
+            .get(number + 1)
+            .map_or(start_time_ms + LAST_SUBTITLE_MS, |&(start, _)| start);
+        let end_time_ms = decoded
+            .duration_ms
+            .map_or(next_start_ms, |duration| start_time_ms + duration);
+
+        subtitles.push(Subtitle::bitmap(
+            start_time_ms,
+            end_time_ms,
+            decoded.bitmap,
+            decoded.corner,
+            index.video_size,
+        ));
+    }
+
+    subtitles
+}
+
+fn parse_index(idx: &str) -> Index {
+    let mut index = Index {
+        video_size: Size::new(720.0, 480.0),
+        palette: [[0; 3]; 16],
+        entries: Vec::new(),
+    };
+    let mut languages = 0;
+
+    for line in idx.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+
+        match key.trim() {
+            "size" => {
+                if let Some((width, height)) = value.split_once('x')
+                    && let (Ok(width), Ok(height)) = (width.parse(), height.parse())
+                {
+                    index.video_size = Size::new(width, height);
+                }
+            }
+            "palette" => {
+                for (slot, color) in index.palette.iter_mut().zip(value.split(',')) {
+                    if let Ok(rgb) = u32::from_str_radix(color.trim(), 16) {
+                        *slot = [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8];
+                    }
+                }
+            }
+            "id" => languages += 1,
+            "timestamp" if languages <= 1 => {
+                // timestamp: 00:00:01:101, filepos: 000000000
+                let Some((time, filepos)) = value.split_once(',') else {
+                    continue;
+                };
+                let parts = time
+                    .split(':')
+                    .map(|part| part.trim().parse::<u32>().ok().map(i128::from))
+                    .collect::<Option<Vec<i128>>>();
+                let filepos = filepos
+                    .trim()
+                    .strip_prefix("filepos:")
+                    .and_then(|filepos| usize::from_str_radix(filepos.trim(), 16).ok());
+                if let (Some([hh, mm, ss, ms]), Some(filepos)) = (parts.as_deref(), filepos) {
+                    index
+                        .entries
+                        .push((hh * 3600000 + mm * 60000 + ss * 1000 + ms, filepos));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    index
+}
+
+/// Collects one subtitle packet, which can be split over several
+/// PES packets of the program stream.
+fn read_packet(mut data: &[u8]) -> Option<Vec<u8>> {
+    let mut packet = Vec::new();
+    let mut size = None;
+
+    while size.is_none_or(|size| packet.len() < size) {
+        if data.len() < 6 || data[..3] != [0x00, 0x00, 0x01] {
+            return None;
+        }
+        match data[3] {
+            // Pack header, MPEG-2 has stuffing after it, MPEG-1 does not
+            0xba => {
+                let length = if data.get(4)? & 0xc0 == 0x40 {
+                    14 + usize::from(data.get(13)? & 0x07)
+                } else {
+                    12
+                };
+                data = data.get(length..)?;
+            }
+            // Private stream 1 carries the subtitles
+            0xbd => {
+                let length = usize::from(u16::from_be_bytes([data[4], data[5]]));
+                let pes = data.get(6..6 + length)?;
+                let header_length = usize::from(*pes.get(2)?);
+                // Skip the PES header and the substream id
+                let payload = pes.get(3 + header_length + 1..)?;
+                packet.extend_from_slice(payload);
+                if size.is_none() && packet.len() >= 2 {
+                    size = Some(usize::from(u16::from_be_bytes([packet[0], packet[1]])));
+                }
+                data = &data[6 + length..];
+            }
+            _ => {
+                let length = usize::from(u16::from_be_bytes([data[4], data[5]]));
+                data = data.get(6 + length..)?;
+            }
+        }
+    }
+
+    packet.truncate(size?);
+    Some(packet)
+}
+
+struct DecodedPacket {
+    bitmap: Bitmap,
+    corner: Point,
+    duration_ms: Option<i128>,
+}
+
+/// Runs the control sequences of a subtitle packet and decodes its image.
+fn decode_packet(packet: &[u8], palette: &[[u8; 3]; 16]) -> Option<DecodedPacket> {
+    let control_offset = usize::from(u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]));
+
+    let mut colors = [0u8; 4];
+    let mut alphas = [0u8; 4];
+    let mut area = None;
+    let mut fields = None;
+    let mut duration_ms = None;
+
+    let mut offset = control_offset;
+    loop {
+        let sequence = packet.get(offset..)?;
+        if sequence.len() < 4 {
+            break;
+        }
+        // Delays are in units of 1024 / 90000 seconds
+        let delay_ms = i128::from(u16::from_be_bytes([sequence[0], sequence[1]])) * 1024 / 90;
+        let next_offset = usize::from(u16::from_be_bytes([sequence[2], sequence[3]]));
+
+        let mut commands = sequence[4..].iter().copied();
+        while let Some(command) = commands.next() {
+            match command {
+                0x00 | 0x01 => {}
+                0x02 => duration_ms = Some(delay_ms),
+                0x03 | 0x04 => {
+                    let (high, low) = (commands.next()?, commands.next()?);
+                    let nibbles = [low & 0x0f, low >> 4, high & 0x0f, high >> 4];
+                    if command == 0x03 {
+                        colors = nibbles;
+                    } else {
+                        alphas = nibbles;
+                    }
+                }
+                0x05 => {
+                    let bytes = [(); 6].map(|_| commands.next().unwrap_or_default());
+                    let x1 = u16::from(bytes[0]) << 4 | u16::from(bytes[1]) >> 4;
+                    let x2 = (u16::from(bytes[1]) & 0x0f) << 8 | u16::from(bytes[2]);
+                    let y1 = u16::from(bytes[3]) << 4 | u16::from(bytes[4]) >> 4;
+                    let y2 = (u16::from(bytes[4]) & 0x0f) << 8 | u16::from(bytes[5]);
+                    area = Some((x1, y1, x2, y2));
+                }
+                0x06 => {
+                    let bytes = [(); 4].map(|_| commands.next().unwrap_or_default());
+                    fields = Some((
+                        usize::from(u16::from_be_bytes([bytes[0], bytes[1]])),
+                        usize::from(u16::from_be_bytes([bytes[2], bytes[3]])),
+                    ));
+                }
+                _ => break,
+            }
+        }
+
+        if next_offset == offset {
+            break;
+        }
+        offset = next_offset;
+    }
+
+    let (x1, y1, x2, y2) = area?;
+    let (top_field, bottom_field) = fields?;
+    let width = usize::from(x2.checked_sub(x1)?) + 1;
+    let height = usize::from(y2.checked_sub(y1)?) + 1;
+
+    // Index 0 of the four colours is usually the transparent background
+    let rgba = std::array::from_fn::<[u8; 4], 4, _>(|i| {
+        let [r, g, b] = palette[usize::from(colors[i])];
+        [r, g, b, alphas[i] * 17]
+    });
+
+    let mut pixels = vec![0u8; width * height * 4];
+    // Lines are interlaced, even lines in the top field and odd lines in the bottom one
+    for (first_line, field_offset) in [(0, top_field), (1, bottom_field)] {
+        let mut nibbles = Nibbles {
+            data: packet,
+            position: field_offset * 2,
+        };
+        for y in (first_line..height).step_by(2) {
+            let mut x = 0;
+            while x < width {
+                let (length, color) = nibbles.next_run()?;
+                let length = if length == 0 { width - x } else { length };
+                for _ in 0..length.min(width - x) {
+                    let index = (y * width + x) * 4;
+                    pixels[index..index + 4].copy_from_slice(&rgba[color]);
+                    x += 1;
+                }
+            }
+            nibbles.align();
+        }
+    }
+
+    Some(DecodedPacket {
+        bitmap: Bitmap::new(width as u32, height as u32, pixels),
+        corner: Point::new(f32::from(x1), f32::from(y1)),
+        duration_ms,
+    })
+}
+
+/// Reads the 4 bit codes of the DVD subtitle run length encoding.
+struct Nibbles<'a> {
+    data: &'a [u8],
+    /// Position in nibbles.
+    position: usize,
+}
+
+impl Nibbles<'_> {
+    fn next(&mut self) -> Option<u16> {
+        let byte = self.data.get(self.position / 2)?;
+        let nibble = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0x0f
+        };
+        self.position += 1;
+        Some(u16::from(nibble))
+    }
+
+    /// A run is 1 to 4 nibbles, the more leading zeros the longer.
+    /// Its length is 0 when it fills the rest of the line.
+    fn next_run(&mut self) -> Option<(usize, usize)> {
+        let mut code = self.next()?;
+        for limit in [0x4, 0x10, 0x40] {
+            if code >= limit {
+                break;
+            }
+            code = code << 4 | self.next()?;
+        }
+        Some((usize::from(code >> 2), usize::from(code & 0x3)))
+    }
+
+    /// Lines start on a whole byte.
+    fn align(&mut self) {
+        self.position += self.position % 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subtitle::Position;
+
+    use super::*;
+
+    #[test]
+    fn runs_of_every_size() {
+        let mut nibbles = Nibbles {
+            // 1 nibble: 0x7 is 1 pixel of colour 3
+            // 2 nibbles: 0x1_2 is 4 pixels of colour 2
+            // 3 nibbles: 0x0_4_1 is 16 pixels of colour 1
+            // 4 nibbles: 0x0_1_0_3 is 64 pixels of colour 3
+            // 0x0_0_0_2 fills the rest of the line with colour 2
+            data: &[0x71, 0x20, 0x41, 0x01, 0x03, 0x00, 0x02],
+            position: 0,
+        };
+        assert_eq!(nibbles.next_run(), Some((1, 3)));
+        assert_eq!(nibbles.next_run(), Some((4, 2)));
+        assert_eq!(nibbles.next_run(), Some((16, 1)));
+        assert_eq!(nibbles.next_run(), Some((64, 3)));
+        assert_eq!(nibbles.next_run(), Some((0, 2)));
+        assert_eq!(nibbles.next_run(), None);
+    }
+
+    #[test]
+    fn lines_start_on_a_whole_byte() {
+        let mut nibbles = Nibbles {
+            data: &[0x70, 0x50],
+            position: 0,
+        };
+        assert_eq!(nibbles.next_run(), Some((1, 3)));
+        nibbles.align();
+        assert_eq!(nibbles.next_run(), Some((1, 1)));
+    }
+
+    #[test]
+    fn one_index_entry() {
+        let idx = "size: 720x576
+palette: 000000, ffffff
+id: en, index: 0
+timestamp: 00:00:01:500, filepos: 000000000
+";
+        let control = [
+            // Shown right away, the next sequence is at 30
+            [0, 0, 0, 30].as_slice(),
+            // Colour 1 is palette entry 1 and opaque
+            &[0x03, 0x00, 0x10, 0x04, 0x00, 0xf0],
+            // From (100, 50) to (101, 51)
+            &[0x05, 0x06, 0x40, 0x65, 0x03, 0x20, 0x33],
+            // The top field at byte 4 and the bottom one at byte 5
+            &[0x06, 0, 4, 0, 5],
+            &[0x01, 0xff],
+            // Gone after 45 * 1024 / 90 = 512 ms
+            &[0, 45, 0, 30, 0x02, 0xff],
+        ]
+        .concat();
+        // Two pixels of colour 1 on each line
+        let image = [0x90, 0x90];
+        let size = (4 + image.len() + control.len()) as u16;
+        let packet = [
+            size.to_be_bytes().as_slice(),
+            &(4 + image.len() as u16).to_be_bytes(),
+            &image,
+            &control,
+        ]
+        .concat();
+        let pes = [
+            // A PES header with 5 bytes of PTS, then the substream
+            [0x81, 0x80, 5, 0, 0, 0, 0, 0, 0x20].as_slice(),
+            &packet,
+        ]
+        .concat();
+        let sub = [
+            // MPEG-2 pack header without stuffing
+            [0, 0, 1, 0xba, 0x44, 0, 0, 0, 0, 0, 0, 0, 0, 0xf8].as_slice(),
+            &[0, 0, 1, 0xbd],
+            &(pes.len() as u16).to_be_bytes(),
+            &pes,
+        ]
+        .concat();
+        assert!(is_vobsub(&sub));
+
+        let subtitles = parse(idx, &sub);
+        assert_eq!(subtitles.len(), 1);
+        assert_eq!(
+            (subtitles[0].start_time_ms, subtitles[0].end_time_ms),
+            (1500, 2012)
+        );
+        let placement = subtitles[0].placement.unwrap();
+        assert_eq!(
+            placement.position,
+            Some(Position::Fixed(Point::new(100.0, 50.0)))
+        );
+        assert_eq!(placement.resolution, Size::new(720.0, 576.0));
+    }
+}
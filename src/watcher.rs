@@ -0,0 +1,71 @@
+use std::{path::PathBuf, time::Duration};
+
+use iced::{
+    Subscription,
+    futures::{SinkExt, StreamExt, channel::mpsc},
+    stream,
+};
+use notify_debouncer_mini::{DebounceEventResult, new_debouncer, notify::RecursiveMode};
+
+/// Editors save in several steps (truncate, write, rename), a change is
+/// only reported once the file has been quiet this long.
+const QUIET_TIME: Duration = Duration::from_millis(200);
+
+/// What the watch of a file reports.
+pub enum Change {
+    Modified(PathBuf),
+    /// The file cannot be watched, with why.
+    Failed(String),
+}
+
+/// Reports every change of the file on disk, or once that it cannot be watched.
+pub fn watch(path: PathBuf) -> Subscription<Change> {
+    Subscription::run_with_id(
+        path.clone(),
+        stream::channel(1, move |mut output| async move {
+            let watched = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            // Saving by renaming a new file over the old one ends a watch on
+            // the file itself, so the folder it is in is watched instead
+            let (Some(folder), Some(file_name)) = (watched.parent(), watched.file_name()) else {
+                return;
+            };
+
+            let (sender, mut changes) = mpsc::unbounded();
+            let file_name = file_name.to_os_string();
+            let debouncer = new_debouncer(QUIET_TIME, move |result: DebounceEventResult| {
+                if let Ok(events) = result
+                    && events
+                        .iter()
+                        .any(|event| event.path.file_name() == Some(&file_name))
+                {
+                    let _ = sender.unbounded_send(());
+                }
+            });
+            let watching = debouncer.and_then(|mut debouncer| {
+                debouncer
+                    .watcher()
+                    .watch(folder, RecursiveMode::NonRecursive)?;
+                Ok(debouncer)
+            });
+            // Dropping the debouncer ends the watch, so it is kept until the end
+            let _debouncer = match watching {
+                Ok(debouncer) => debouncer,
+                Err(error) => {
+                    let _ = output
+                        .send(Change::Failed(format!(
+                            "Cannot watch {} for changes: {error}",
+                            path.display()
+                        )))
+                        .await;
+                    return;
+                }
+            };
+
+            while changes.next().await.is_some() {
+                if output.send(Change::Modified(path.clone())).await.is_err() {
+                    break;
+                }
+            }
+        }),
+    )
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use iced::{Point, Size};
+
+use crate::{
+    html,
+    subtitle::{self, Placement, Position, RunStyle, Subtitle},
+};
+
+/// Height of one `line:` step when it is a line number instead of a
+/// percentage, as a percentage of the video height.
+const LINE_HEIGHT_PERCENT: f32 = 5.33;
+
+/// Parses a WebVTT file. Cue settings are turned into a [`Placement`] in
+/// a 100 by 100 space, so percentages can be used as they are.
+pub fn parse(data: &str) -> Vec<Subtitle> {
+    let data = data
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+
+    let mut classes: HashMap<String, RunStyle> = HashMap::new();
+    let mut subtitles = Vec::new();
+
+    for block in blocks(&data) {
+        let first = block[0];
+        if first.starts_with("WEBVTT") || first.starts_with("NOTE") || first == "REGION" {
+            continue;
+        }
+        if first == "STYLE" {
+            parse_style_block(&block[1..].join("\n"), &mut classes);
+            continue;
+        }
+
+        // An optional cue identifier comes before the timing line
+        let Some(timing_index) = block.iter().take(2).position(|line| line.contains("-->")) else {
+            continue;
+        };
+        let Some((start_time_ms, end_time_ms, settings)) = parse_timing(block[timing_index]) else {
+            continue;
+        };
+
+        let payload = block[timing_index + 1..].join("\n");
+        let mut runs = html::parse_markup_with_classes(&payload, &classes);
+        if let Some(global) = classes.get("") {
+            for run in runs.iter_mut() {
+                let mut style = global.clone();
+                html::merge_style(&mut style, &run.style);
+                run.style = style;
+            }
+        }
+
+        subtitles.push(Subtitle {
+            runs,
+            placement: parse_settings(settings),
+            ..Subtitle::new(start_time_ms, end_time_ms)
+        });
+    }
+
+    subtitles
+}
+
+/// Groups the lines of the file into blocks separated by blank lines.
+fn blocks(data: &str) -> Vec<Vec<&str>> {
+    let mut blocks = Vec::new();
+    let mut current = Vec::new();
+
+    for line in data.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    blocks
+}
+
+/// `00:01.000 --> 00:04.000 line:0 align:start` into start, end and settings.
+fn parse_timing(line: &str) -> Option<(i128, i128, &str)> {
+    let (start, rest) = line.split_once("-->")?;
+    let rest = rest.trim_start();
+    let (end, settings) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+    Some((parse_timestamp(start)?, parse_timestamp(end)?, settings))
+}
+
+/// `hh:mm:ss.ttt` or `mm:ss.ttt`.
+fn parse_timestamp(input: &str) -> Option<i128> {
+    let (clock, fraction) = input.trim().split_once('.')?;
+    let parts = clock
+        .split(':')
+        .map(|part| part.parse::<i128>().ok())
+        .collect::<Option<Vec<i128>>>()?;
+
+    let (hh, mm, ss) = match parts[..] {
+        [hh, mm, ss] => (hh, mm, ss),
+        [mm, ss] => (0, mm, ss),
+        _ => return None,
+    };
+    if mm >= 60 || ss >= 60 || fraction.len() != 3 {
+        return None;
+    }
+    let ms = fraction.parse::<i128>().ok()?;
+
+    Some(hh * 3600000 + mm * 60000 + ss * 1000 + ms)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Anchor {
+    Start,
+    Center,
+    End,
+}
+
+impl Anchor {
+    fn parse(value: &str) -> Option<Anchor> {
+        match value {
+            "start" | "left" | "line-left" => Some(Anchor::Start),
+            "center" | "middle" => Some(Anchor::Center),
+            "end" | "right" | "line-right" => Some(Anchor::End),
+            _ => None,
+        }
+    }
+}
+
+/// Turns the `line`, `position`, `align` and `size` cue settings into a
+/// placement. Cues without settings keep the default placement.
+fn parse_settings(settings: &str) -> Option<Placement> {
+    let mut align = Anchor::Center;
+    // Vertical position in percent and which edge of the cue it is for
+    let mut line: Option<(f32, Anchor)> = None;
+    let mut position: Option<(f32, Option<Anchor>)> = None;
+    let mut size: Option<f32> = None;
+
+    for setting in settings.split_whitespace() {
+        let Some((key, value)) = setting.split_once(':') else {
+            continue;
+        };
+        let (value, anchor) = match value.split_once(',') {
+            Some((value, anchor)) => (value, Anchor::parse(anchor)),
+            None => (value, None),
+        };
+
+        match key {
+            "align" => align = Anchor::parse(value).unwrap_or(align),
+            "line" => {
+                line = if let Some(percent) = parse_percent(value) {
+                    Some((percent, anchor.unwrap_or(Anchor::Start)))
+                } else if let Ok(number) = value.parse::<f32>() {
+                    // Negative line numbers count from the bottom
+                    if number < 0.0 {
+                        Some((
+                            100.0 + (number + 1.0) * LINE_HEIGHT_PERCENT,
+                            anchor.unwrap_or(Anchor::End),
+                        ))
+                    } else {
+                        Some((
+                            number * LINE_HEIGHT_PERCENT,
+                            anchor.unwrap_or(Anchor::Start),
+                        ))
+                    }
+                } else {
+                    None
+                };
+            }
+            "position" => position = parse_percent(value).map(|percent| (percent, anchor)),
+            "size" => size = parse_percent(value),
+            _ => {}
+        }
+    }
+
+    if line.is_none() && position.is_none() && size.is_none() && align == Anchor::Center {
+        return None;
+    }
+
+    let horizontal = position.and_then(|(_, anchor)| anchor).unwrap_or(align);
+    let vertical = line.map_or(Anchor::End, |(_, anchor)| anchor);
+    let column = match horizontal {
+        Anchor::Start => 1,
+        Anchor::Center => 2,
+        Anchor::End => 3,
+    };
+    let row = match vertical {
+        Anchor::End => 0,
+        Anchor::Center => 3,
+        Anchor::Start => 6,
+    };
+
+    let mut placement = Placement {
+        alignment: row + column,
+        position: None,
+        margin_l: 0.0,
+        margin_r: 0.0,
+        margin_v: 0.0,
+        resolution: Size::new(100.0, 100.0),
+    };
+
+    if line.is_some() || position.is_some() {
+        let x = position.map_or(
+            match horizontal {
+                Anchor::Start => 0.0,
+                Anchor::Center => 50.0,
+                Anchor::End => 100.0,
+            },
+            |(x, _)| x,
+        );
+        let y = line.map_or(100.0, |(y, _)| y);
+        placement.position = Some(Position::Fixed(Point::new(x, y)));
+    } else if let Some(size) = size {
+        let free = (100.0 - size).max(0.0);
+        match horizontal {
+            Anchor::Start => placement.margin_r = free,
+            Anchor::Center => {
+                placement.margin_l = free / 2.0;
+                placement.margin_r = free / 2.0;
+            }
+            Anchor::End => placement.margin_l = free,
+        }
+    }
+
+    Some(placement)
+}
+
+fn parse_percent(value: &str) -> Option<f32> {
+    value.strip_suffix('%')?.parse::<f32>().ok()
+}
+
+/// Reads `::cue(.class) { ... }` rules from a `STYLE` block, a plain
+/// `::cue` rule is stored under the empty class name.
+fn parse_style_block(css: &str, classes: &mut HashMap<String, RunStyle>) {
+    for rule in css.split('}') {
+        let Some((selectors, declarations)) = rule.split_once('{') else {
+            continue;
+        };
+
+        let mut style = RunStyle::default();
+        for declaration in declarations.split(';') {
+            let Some((property, value)) = declaration.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match property.trim().to_ascii_lowercase().as_str() {
+                "color" => style.color = html::parse_color(value),
+                "font-weight" => {
+                    style.bold = value.eq_ignore_ascii_case("bold")
+                        || value.eq_ignore_ascii_case("bolder")
+                        || value.parse::<u32>().is_ok_and(|weight| weight >= 600)
+                }
+                "font-style" => {
+                    style.italic = value.eq_ignore_ascii_case("italic")
+                        || value.eq_ignore_ascii_case("oblique")
+                }
+                "text-decoration" => {
+                    let value = value.to_ascii_lowercase();
+                    style.underline = value.contains("underline");
+                    style.strikethrough = value.contains("line-through");
+                }
+                "font-family" => {
+                    let family = value.split(',').next().unwrap_or_default();
+                    let family = family.trim().trim_matches(['"', '\'']);
+                    if !family.is_empty() {
+                        style.family = Some(subtitle::font_family(family));
+                    }
+                }
+                "font-size" => {
+                    style.size = if let Some(percent) = parse_percent(value) {
+                        Some(percent / 100.0)
+                    } else {
+                        value
+                            .strip_suffix("em")
+                            .and_then(|em| em.parse::<f32>().ok())
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for selector in selectors.split(',') {
+            let selector = selector.trim();
+            let class = if selector == "::cue" {
+                Some("")
+            } else {
+                selector
+                    .strip_prefix("::cue(.")
+                    .and_then(|class| class.strip_suffix(')'))
+            };
+            if let Some(class) = class {
+                classes.insert(class.to_string(), style.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(settings: &str) -> Option<Placement> {
+        let cue = format!("WEBVTT\n\n00:01.000 --> 00:02.000 {settings}\nText\n");
+        parse(&cue).remove(0).placement
+    }
+
+    #[test]
+    fn timings_with_and_without_hours() {
+        let subtitles = parse(
+            "WEBVTT\n\n\
+             01:02:03.456 --> 01:02:04.000\nWith hours\n\n\
+             intro\n00:05.250 --> 00:07.000\nWithout, after an identifier\n\n\
+             00:61.000 --> 00:62.000\nNot a time\n",
+        );
+        let times = subtitles
+            .iter()
+            .map(|subtitle| (subtitle.start_time_ms, subtitle.end_time_ms))
+            .collect::<Vec<(i128, i128)>>();
+        assert_eq!(times, [(3_723_456, 3_724_000), (5250, 7000)]);
+        assert_eq!(subtitles[1].plain_text(), "Without, after an identifier");
+    }
+
+    #[test]
+    fn no_settings_keep_the_default_placement() {
+        assert_eq!(placement(""), None);
+        assert_eq!(placement("align:center"), None);
+    }
+
+    #[test]
+    fn line_and_position_settings() {
+        let placed = placement("line:10% position:25%,line-left").unwrap();
+        assert_eq!(placed.alignment, 7);
+        assert_eq!(
+            placed.position,
+            Some(Position::Fixed(Point::new(25.0, 10.0)))
+        );
+        assert_eq!(placed.resolution, Size::new(100.0, 100.0));
+
+        // Negative line numbers count up from the bottom
+        let placed = placement("line:-1 align:end").unwrap();
+        assert_eq!(placed.alignment, 3);
+        assert_eq!(
+            placed.position,
+            Some(Position::Fixed(Point::new(100.0, 100.0)))
+        );
+    }
+
+    #[test]
+    fn size_setting_becomes_margins() {
+        let placed = placement("size:60% align:start").unwrap();
+        assert_eq!(placed.alignment, 1);
+        assert_eq!(placed.position, None);
+        assert_eq!((placed.margin_l, placed.margin_r), (0.0, 40.0));
+
+        let placed = placement("size:50%").unwrap();
+        assert_eq!(placed.alignment, 2);
+        assert_eq!((placed.margin_l, placed.margin_r), (25.0, 25.0));
+    }
+}